@@ -0,0 +1,158 @@
+use engine::ecs::system::System;
+use engine::ecs::BoxCollider2DComponent;
+use engine::generational_index::generational_index::GenerationalIndex;
+use nalgebra::{Vector2};
+use failure::Error;
+use engine::game_state::GameState;
+use engine::input::CursorPosition;
+use engine::utilities::vector_utils::{get_direction_2d, get_projection_2d};
+use crate::events::CollisionEvent;
+
+pub struct CheckBoxColliderSystem;
+
+impl<'a> System<'a> for CheckBoxColliderSystem {
+
+    type SystemInput = &'a mut GameState;
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let cursor = input.resource::<CursorPosition>().world;
+
+        if let Some((entity, cursor_offset)) = CheckBoxColliderSystem::check_sat_collision(input, cursor) {
+            input.send_event(CollisionEvent { entity, cursor_offset });
+        }
+
+        Ok(())
+    }
+}
+
+impl CheckBoxColliderSystem {
+
+    /// Returns the first entity whose collider contains the point, along with the offset from the
+    /// point to the collider.
+    pub fn check_sat_collision(state: &GameState, collision_point : Vector2<f32>) -> Option<(GenerationalIndex, Vector2<f32>)> {
+
+        let colliders = state.try_get_map::<BoxCollider2DComponent>()?;
+
+        for (gen_idx, collider) in colliders.iter() {
+
+            let position = collider.position;
+            let corners = &collider.corners;
+            let normals = CheckBoxColliderSystem::get_normals(corners);
+
+            let collisions = CheckBoxColliderSystem::get_sat_projections(&[collision_point], corners, &normals);
+
+            let collision_one =
+                collisions.shape_one.first_axis_max > collisions.shape_two.first_axis_min
+                    && collisions.shape_one.first_axis_min < collisions.shape_two.first_axis_max;
+            let collision_two = collisions.shape_one.second_axis_max > collisions.shape_two.second_axis_min
+                && collisions.shape_one.second_axis_min < collisions.shape_two.second_axis_max;
+
+            if collision_one && collision_two {
+
+                let heading = Vector2::new(collision_point.x, collision_point.y);
+                let distance = Vector2::magnitude(&heading);
+                let direction = heading / distance;
+
+                return Some((gen_idx, position - direction * distance))
+            }
+        }
+
+        None
+    }
+
+    pub fn get_normals(corners : &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+
+        let normal_y = get_direction_2d(corners[1], corners[0]);
+        let normal_y = Vector2::new(-normal_y.y, normal_y.x);
+
+        let normal_x = get_direction_2d(corners[1], corners[3]);
+        let normal_x = Vector2::new(normal_x.y, -normal_x.x);
+
+        vec![normal_y, normal_x]
+    }
+
+    pub fn get_sat_projections(shape_one_crnrs : &[Vector2<f32>], shape_two_crnrs: &[Vector2<f32>], axes : &[Vector2<f32>]) -> SatCollisions{
+
+        let shape_one =
+            CheckBoxColliderSystem::get_crnr_projections(shape_one_crnrs, axes);
+
+        let shape_two =
+            CheckBoxColliderSystem::get_crnr_projections(shape_two_crnrs, axes);
+
+        SatCollisions {
+            shape_one,
+            shape_two
+        }
+    }
+
+    pub fn get_crnr_projections(corners: &[Vector2<f32>], axes : &[Vector2<f32>]) -> SatShape{
+
+        let mut projected_crnrs_x = Vec::new();
+        let mut projected_crnrs_y = Vec::new();
+
+        for corner in corners {
+            projected_crnrs_x.push(get_projection_2d(corner, &axes[0]));
+            projected_crnrs_y.push(get_projection_2d(corner, &axes[1]));
+        }
+
+        let min_projection_one = CheckBoxColliderSystem::get_min(&projected_crnrs_x, axes[0]);
+        let max_projection_one = CheckBoxColliderSystem::get_max(&projected_crnrs_x, axes[0]);
+        let min_projection_two = CheckBoxColliderSystem::get_min(&projected_crnrs_y, axes[1]);
+        let max_projection_two = CheckBoxColliderSystem::get_max(&projected_crnrs_y, axes[1]);
+
+        SatShape {
+            first_axis_min: min_projection_one,
+            first_axis_max: max_projection_one,
+            second_axis_min: min_projection_two,
+            second_axis_max: max_projection_two
+        }
+    }
+
+    pub fn get_min(corners : &[Vector2<f32>], axis: Vector2<f32>) -> f32 {
+
+        let mut min_dot = corners[0].dot(&axis);
+
+        for corner in corners {
+
+            let dot = corner.dot(&axis);
+
+            if dot < min_dot {
+                min_dot = dot;
+            }
+        }
+
+        min_dot
+    }
+
+    pub fn get_max(corners : &[Vector2<f32>], axis: Vector2<f32>) -> f32 {
+
+        let mut max_dot = corners[0].dot(&axis);
+
+        for corner in corners {
+
+            let dot  = corner.dot(&axis);
+            if dot > max_dot {
+                max_dot = dot;
+            }
+        }
+
+        max_dot
+    }
+}
+
+#[derive(Clone)]
+pub struct SatCollisions {
+
+    pub shape_one : SatShape,
+    pub shape_two : SatShape,
+}
+
+#[derive(Clone)]
+pub struct SatShape {
+
+    pub first_axis_min : f32,
+    pub first_axis_max : f32,
+    pub second_axis_min : f32,
+    pub second_axis_max : f32,
+}
\ No newline at end of file
@@ -0,0 +1,54 @@
+//! COMPONENTS
+//! The editor's own components. They are registered with the app in game(), which is what lets
+//! them be snapshotted, reflected and (for look-ats) saved in scenes.
+
+use engine::ecs::Component;
+use engine::scene::SceneComponent;
+use engine::{reflect_struct, snapshot_by_clone};
+use failure::Error;
+use nalgebra::Vector2;
+use serde_json::{json, Value};
+
+/// Marks an entity as selected. Its color is swapped for selected_color while the component is on
+/// the entity, and put back to origin_color when it is removed.
+/// SEE: hooks
+#[derive(Clone)]
+pub struct SelectedComponent {
+
+    pub selected_color : (f32, f32, f32, f32),
+    pub origin_color : (f32, f32, f32, f32),
+    pub cursor_offset : Vector2<f32>
+}
+
+impl Component for SelectedComponent {}
+
+/// Turns a selected entity toward the focus point.
+#[derive(Clone)]
+pub struct LookAtPositionComponent {
+
+    pub focus_point: Vector2<f32>
+}
+
+impl Component for LookAtPositionComponent {}
+
+/// Saved as "look_at": [x, y].
+impl SceneComponent for LookAtPositionComponent {
+
+    fn to_scene(&self) -> Value {
+        json!([self.focus_point.x, self.focus_point.y])
+    }
+
+    fn from_scene(value : Value) -> Result<Self, Error> {
+
+        let focus_point : [f32; 2] = serde_json::from_value(value)?;
+
+        Ok(LookAtPositionComponent { focus_point : Vector2::new(focus_point[0], focus_point[1]) })
+    }
+}
+
+snapshot_by_clone!(SelectedComponent, LookAtPositionComponent);
+
+reflect_struct!(
+    SelectedComponent { selected_color, origin_color, cursor_offset };
+    LookAtPositionComponent { focus_point }
+);
@@ -0,0 +1,59 @@
+use engine::ecs::system::System;
+use engine::events::event_channel::{Events, EventReader};
+use engine::game_state::{GameState, Entity};
+use engine::history::{History, Edit};
+use failure::Error;
+use crate::events::{SelectionChanged, EntitySpawned};
+
+/// Opens an undo group for newly selected entities, so every set made while dragging them is undone
+/// in one go. Only sets on those entities join the group, which is closed once the left mouse
+/// button is released.
+pub struct StartDragSystem;
+
+impl<'a> System<'a> for StartDragSystem {
+
+    type SystemInput = (&'a mut GameState, &'a mut EventReader<SelectionChanged>);
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let (state, reader) = input;
+
+        let selected = reader.read(state.resource::<Events<SelectionChanged>>())
+            .filter(|change| change.selected)
+            .map(|change| change.entity)
+            .collect::<Vec<Entity>>();
+
+        if let Some(history) = state.try_resource_mut::<History>() {
+            if !selected.is_empty() {
+                history.begin_group(&selected);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Records every entity the game's systems spawn, so the spawn can be undone.
+pub struct RecordSpawnsSystem;
+
+impl<'a> System<'a> for RecordSpawnsSystem {
+
+    type SystemInput = (&'a mut GameState, &'a mut EventReader<EntitySpawned>);
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let (state, reader) = input;
+
+        let spawned = reader.read(state.resource::<Events<EntitySpawned>>())
+            .map(|spawned| spawned.entity)
+            .collect::<Vec<Entity>>();
+
+        for entity in spawned.iter() {
+            if state.is_alive(entity) {
+                state.record_edit(Edit::spawn(state, entity));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,20 +1,36 @@
+//! APP
+//! Builds and runs a game. The engine only brings its own systems (movement, transforms, textures
+//! and rendering) and the resources and events they need - the game adds everything else, e.g:
+//!
+//! App::new()
+//!     .with_window(WindowProperties::new("Game", 1280, 720))
+//!     .insert_resource(camera)
+//!     .add_startup_system(SystemDescriptor::new("load_scene", load_scene))
+//!     .add_system(Stage::Update, SystemDescriptor::new("spawn", spawn))
+//!     .run()
+//!
+//! The same app can also run headless, record its input or replay a recording.
+//! SEE: headless, input::recording
+//!
+//! The game's own components are registered through the builder too (hooks, snapshots, reflection
+//! and scenes), so everything is in place before the startup systems run.
+//!
+//! Resources are inserted before the startup systems run. The render system needs an
+//! OrthographicCameraComponent resource, which is up to the game to insert.
+
 // Crates
 extern crate gl;
 extern crate failure;
 
 // Internal crates:
-use crate::ecs::{PositionComponent, ColorComponent, RenderComponent, TextureMixComponent};
 use crate::ecs::*;
 use crate::ecs::system::System;
-use crate::events::window_event::WindowEvent;
+use crate::ecs::schedule::{Schedule, SystemDescriptor, Stage};
+use crate::events::window_event::{WindowEvent, WindowCallback};
 use crate::game_state::GameState;
 use crate::platform::windows::windows_window;
 use crate::window::{WindowProperties, WindowTrait};
-use crate::platform::windows::windows_window::{WindowsWindow};
-use crate::sdl2::mouse::MouseButton;
-use crate::input::{MouseInput, KeyCode};
-use crate::nalgebra::{Vector3, Vector2};
-use crate::utilities::vector_utils::*;
+use crate::input::CursorPosition;
 
 // Use
 use failure::Error;
@@ -22,24 +38,228 @@ use std::collections::VecDeque;
 use std::time::{Duration};
 use crate::input::input_handler::*;
 use crate::input;
-use crate::utilities::camera_utils;
-use crate::ecs::look_at_position_system::{LookAtPositionSystem, UpdateFocusPointSystem};
+use crate::time::Time;
+use crate::profiler::{Profiler, ProfileScope};
+use crate::headless::HeadlessRunner;
+use crate::input::recording::{InputRecording, FrameInput, WindowInput};
+use crate::renderer;
+use crate::ecs::registry::ComponentHook;
+use crate::ecs::snapshot::Snapshot;
+use crate::ecs::reflect::Reflect;
+use crate::scene::SceneComponent;
+
+pub struct App {
+
+    window : WindowProperties,
+    schedule : Schedule,
+    setup : Vec<Setup>
+}
+
+/// Sets up part of the game state, e.g. inserts a resource or registers a hook.
+type Setup = Box<dyn FnOnce(&mut GameState)>;
+
+impl Default for App {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl App {
+
+    /// An app with the engine's systems, which opens the default window.
+    pub fn new() -> App {
+
+        App { window : window_base!(), schedule : engine_schedule(), setup : Vec::new() }
+    }
+
+    pub fn with_window(mut self, properties : WindowProperties) -> Self {
+
+        self.window = properties;
+        self
+    }
+
+    /// Adds a system which runs once, before the first frame.
+    pub fn add_startup_system(mut self, system : SystemDescriptor) -> Self {
+
+        self.schedule.add_startup_system(system);
+        self
+    }
+
+    pub fn add_system(mut self, stage : Stage, system : SystemDescriptor) -> Self {
+
+        self.schedule.add_system(stage, system);
+        self
+    }
+
+    /// Adds a world resource, replacing any the engine inserts of the same type.
+    pub fn insert_resource<T : 'static>(mut self, resource : T) -> Self {
+
+        self.setup.push(Box::new(move |state : &mut GameState| { state.insert_resource(resource); }));
+        self
+    }
+
+    /// Adds an event channel.
+    pub fn add_event<T : 'static>(mut self) -> Self {
+
+        self.setup.push(Box::new(|state : &mut GameState| state.add_event::<T>()));
+        self
+    }
+
+    /// Adds a hook which runs whenever a T is added to an entity.
+    /// SEE: GameState::on_add
+    pub fn on_add<T : Component>(mut self, hook : ComponentHook) -> Self {
+
+        self.setup.push(Box::new(move |state : &mut GameState| state.on_add::<T>(hook)));
+        self
+    }
+
+    /// Adds a hook which runs whenever a T is removed from an entity.
+    /// SEE: GameState::on_remove
+    pub fn on_remove<T : Component>(mut self, hook : ComponentHook) -> Self {
 
+        self.setup.push(Box::new(move |state : &mut GameState| state.on_remove::<T>(hook)));
+        self
+    }
+
+    /// Lets the game's components of type T be captured in snapshots.
+    pub fn register_snapshot<T : Snapshot>(mut self) -> Self {
+
+        self.setup.push(Box::new(|state : &mut GameState| state.register_snapshot::<T>()));
+        self
+    }
+
+    /// Makes the game's components of type T reachable through reflection.
+    pub fn register_reflect<T : Component + Reflect>(mut self) -> Self {
+
+        self.setup.push(Box::new(|state : &mut GameState| state.register_reflect::<T>()));
+        self
+    }
+
+    /// Lets the game's components of type T be saved in scenes and prefabs under the given name.
+    pub fn register_scene_component<T : SceneComponent>(mut self, name : &'static str) -> Self {
+
+        self.setup.push(Box::new(move |state : &mut GameState| state.register_scene_component::<T>(name)));
+        self
+    }
+
+    /// Opens the window and runs the game until it is closed.
+    pub fn run(self) -> Result<(), Error> {
+
+        let (window, schedule, state) = self.build();
+
+        run_loop(window, schedule, state, None)
+    }
+
+    /// Runs the game like run(), saving every frame's input to a file when the window is closed.
+    pub fn record(self, path : &str) -> Result<(), Error> {
+
+        let (window, schedule, state) = self.build();
+
+        let mut recording = InputRecording::new();
+
+        run_loop(window, schedule, state, Some(&mut recording))?;
+
+        recording.save(path)
+    }
+
+    /// Runs the startup systems without a window, handing back a runner which steps the game.
+    pub fn headless(self) -> Result<HeadlessRunner, Error> {
+
+        let (_, schedule, state) = self.build();
+
+        HeadlessRunner::with_state(schedule, state)
+    }
+
+    /// Runs the given number of frames headless, returning the final state.
+    pub fn run_headless(self, frames : u64) -> Result<GameState, Error> {
+
+        let mut runner = self.headless()?;
+
+        runner.run_frames(frames)?;
+
+        Ok(runner.state)
+    }
+
+    /// Replays a recording made by record() headless, failing if the world doesn't end up as it was
+    /// recorded. Returns the final state.
+    pub fn replay(self, path : &str) -> Result<GameState, Error> {
+
+        let recording = InputRecording::load(path)?;
+
+        let mut runner = self.headless()?;
+
+        runner.replay(&recording)?;
+
+        Ok(runner.state)
+    }
+
+    /// Sets up the state with the engine's resources and events, then the game's.
+    fn build(self) -> (WindowProperties, Schedule, GameState) {
+
+        let mut state = GameState::create_initial_state();
+
+        // World resources which any system can reach through the game state.
+        state.insert_resource(InputHandler::new());
+        state.insert_resource(CursorPosition::new());
+        state.insert_resource(Time::new());
+        state.insert_resource(Profiler::new());
+
+        for setup in self.setup {
+            setup(&mut state);
+        }
+
+        (self.window, self.schedule, state)
+    }
+}
+
+/// The systems every app starts with.
+fn engine_schedule() -> Schedule {
+
+    let mut schedule = Schedule::new();
+
+    // UPDATE
+
+    schedule.add_system(Stage::Update, SystemDescriptor::new("texture_update",
+        |state| texture_update_system::TextureUpdateSystem::run(state)));
+
+    // FIXED UPDATE
+
+    schedule
+        .add_system(Stage::FixedUpdate, SystemDescriptor::new("previous_position",
+            |state| position_update_system::PreviousPositionSystem::run(state)))
+
+        .add_system(Stage::FixedUpdate, SystemDescriptor::new("position_update",
+            |state| position_update_system::PositionUpdateSystem::run(state)));
+
+    // POST UPDATE
+
+    schedule.add_system(Stage::PostUpdate, SystemDescriptor::new("transform_propagation",
+        |state| transform_propagation_system::TransformPropagationSystem::run(state)));
+
+    // RENDER
+
+    // Skipped when running headless.
+    schedule.add_system(Stage::Render, SystemDescriptor::new("render", render)
+        .run_if(|_| renderer::gl_loaded()));
+
+    schedule
+}
+
+/// The shortest a frame can take, which caps the frame rate at 144fps.
+const FRAME_BUDGET : Duration = Duration::from_nanos(1_000_000_000 / 144);
 
 /// This is the code for the current event loop.
 /// So far the event loop contains the base SDL struct, an event pump, a window, and a game state object.
-/// So far, it initialises all entities, and has the event loop render three triangles to the screen.
-
-pub fn run() -> Result<(), Error> {
+/// Every frame it processes SDL events, updates the input resources, then runs the schedule.
+fn run_loop(properties : WindowProperties, mut schedule : Schedule, mut game_state : GameState,
+            mut recording : Option<&mut InputRecording>) -> Result<(), Error> {
 
     // Initialise sdl
     let sdl = sdl2::init().unwrap();
 
     // Create the base window for the application.
-    let mut window = windows_window::create_new(window_base!(), &sdl);
-
-    // Initialises the game state.
-    let mut game_state = GameState::create_initial_state();
+    let mut window = windows_window::create_new(properties, &sdl);
 
     // Get the event pump from sdl.
     let mut pump = sdl.event_pump().unwrap();
@@ -48,18 +268,27 @@ pub fn run() -> Result<(), Error> {
     let mut one_time_events: VecDeque<Box<dyn FnMut()>> = VecDeque::new();
 
     // Initialise event queue for the game window.
-    let mut one_time_window_events: VecDeque<Box<dyn FnMut(&mut WindowsWindow)>> = VecDeque::new();
+    let mut one_time_window_events: VecDeque<WindowCallback> = VecDeque::new();
 
     unsafe { gl::Viewport(0, 0, window.data.width as i32, window.data.height as i32); }
 
-    // Sets up the entities in the ECS.
-    let m_camera = GameState::init_test_state(&mut game_state, &window)?;
-
-    let mut input_handler = InputHandler::new();
+    // Startup systems run once the GL context exists, so they can build GL-backed components.
+    schedule.run_startup(&mut game_state)?;
 
     // MAIN LOOP
     'running: loop {
 
+        game_state.resource_mut::<Time>().update();
+
+        // Everything fed in from outside this frame, so it can be recorded and replayed.
+        let mut frame = FrameInput::new();
+
+        frame.delta = game_state.resource::<Time>().unscaled_delta;
+
+        game_state.resource_mut::<Profiler>().begin_frame();
+
+        let event_pump = ProfileScope::start("event_pump");
+
         // Checks for sdl2 events. These are then filtered to appropriate areas to be processed properly.
         for event in pump.poll_iter(){
             // WINDOW EVENTS
@@ -68,111 +297,43 @@ pub fn run() -> Result<(), Error> {
 
                 // All window events are rerouted toward the active window.
                 sdl2::event::Event::Window { timestamp : _ , window_id : _, win_event }
-                => windows_window::process_event(&win_event, &mut WindowEvent { window: &mut window, events: &mut one_time_window_events }),
+                => {
+                    frame.window_events.extend(WindowInput::from_sdl(&win_event));
+                    windows_window::process_event(&win_event, &mut WindowEvent { window: &mut window, events: &mut one_time_window_events })
+                },
 
                 // Breaks the loop.
                 sdl2::event::Event::Quit { .. }=> { break 'running },
 
                 sdl2::event::Event::MouseButtonUp {timestamp: _, window_id: _, which: _ , mouse_btn: button, .. }
-                    => { input_handler.clear_mouse_input(&input::sdl_mouse_to_mouse(&button))},
+                    => { frame.released_buttons.push(input::sdl_mouse_to_mouse(&button)) },
 
-                sdl2::event::Event::KeyUp { timestamp: _, window_id: _ , keycode: code, scancode: scancode, .. }
-                    => { println!("Key Released: {}", code.unwrap()); input_handler.clear_keyboard_input(&input::scancode_to_keycode(&scancode.unwrap()))}
+                sdl2::event::Event::KeyUp { timestamp: _, window_id: _ , keycode: code, scancode, .. }
+                    => { println!("Key Released: {}", code.unwrap()); frame.released_keys.push(input::scancode_to_keycode(&scancode.unwrap()))}
 
                 // TODO
                 _ => ()
             }
         }
 
-        // KEYBOARD INPUT MODULE - NEEDS WORK
-
-        input_handler.update_input_state(&mut pump);
+        event_pump.end(&mut game_state);
 
-        // MOUSE INPUT MODULE - NEEDS WORK
+        // KEYBOARD AND MOUSE INPUT MODULE - NEEDS WORK
 
-        // LEFT CLICK
-        if input_handler.get_mouse_down(&MouseInput::LeftMouse) {
+        frame.keys = input::pressed_keys(&pump);
+        frame.buttons = input::pressed_mouse_buttons(&pump);
 
-            let mouse_coordinates = input::get_mouse_coordinates(&pump);
+        let mouse_coordinates = input::get_mouse_coordinates(&pump);
 
-            // TODO: UPDATE ORTHOGRAPHIC CAMERA WHEN SCREEN IS RESIZED.
-            let screen_coordinates = camera_utils::ortho_screen_to_world_coordinates(
-                &game_state.get::<OrthographicCameraComponent>(&m_camera).unwrap(),
-                mouse_coordinates);
+        frame.cursor = [mouse_coordinates.x, mouse_coordinates.y];
 
-            // CHECK IF MOUSE IS HELD DOWN
+        // TODO: UPDATE ORTHOGRAPHIC CAMERA WHEN SCREEN IS RESIZED.
+        frame.apply(&mut game_state);
 
-            if input_handler.get_mouse_button(&MouseInput::LeftMouse) {
-
-                check_mouse_collision_system::CheckBoxColliderSystem::run((&mut game_state, &screen_coordinates))?;
-
-            } else {
-
-                selection_system::FollowMouseSystem::run((&mut game_state, &screen_coordinates))?;
-            }
+        if let Some(recording) = recording.as_mut() {
+            recording.frames.push(frame);
         }
 
-        // RIGHT CLICK
-        if input_handler.get_mouse_down(&MouseInput::RightMouse) {
-
-            let mouse_coordinates = input::get_mouse_coordinates(&pump);
-
-             let screen_coords = camera_utils::ortho_screen_to_world_coordinates(
-                &game_state.get::<OrthographicCameraComponent>(&m_camera).unwrap(),
-                mouse_coordinates);
-
-            if input_handler.get_mouse_button(&MouseInput::RightMouse) {
-
-                let scale = Vector3::new(50.0, 50.0, 0.0);
-                let position = Vector3::new(screen_coords.x, screen_coords.y, 0.0);
-                let corners = get_box_corners(Vector2::new(position.x,position.y), Vector2::new(scale.x, scale.y));
-
-                selection_system::DeselectSystem::run(&mut game_state);
-
-                let entity = GameState::create_entity(&mut game_state)
-                    .with(RenderComponent {shader_program : triangle_render!(), vertex_array_object : quad!()})
-                    .with(PositionComponent {position})
-                    .with(ScaleComponent {scale})
-                    .with(ColorComponent {color : (0.0, 0.0, 0.0, 0.0) })
-                    .with(VelocityComponent {velocity : Vector3::new(0.0, 0.0, 0.0)})
-                    .with(BoxCollider2DComponent {position: Vector2::new(position.x, position.y), size : Vector2::new(scale.x * 2.0, scale.y * 2.0), corners})
-                    .with(RotationComponent { rotation: Vector3::new(0.0, 0.0, 0.0) })
-                    .with(RotationUpdateComponent { axis: Vector3::new(0.0, 0.0, 1.0), angle: get_rotation_angle_2(Vector2::new(screen_coords.x, screen_coords.y), screen_coords) })
-                    .with(LookAtPositionComponent{ focus_point: screen_coords})
-                    .with(SelectedComponent {
-                        selected_color: (0.5, 0.5, 0.5, 0.5),
-                        origin_color: (0.0, 0.0, 0.0, 0.0),
-                        cursor_offset: Vector2::new(0.0, 0.0)
-                    })
-                    .build();
-            }
-
-            UpdateFocusPointSystem::run((&mut game_state, screen_coords));
-            LookAtPositionSystem::run((&mut game_state));
-        }
-
-        if input_handler.get_keycode(&KeyCode::Space) {
-
-            let position = Vector3::new(0.0, 0.0, 0.0);
-            let scale = Vector3::new(50.0, 50.0, 50.0);
-            let corners = get_box_corners(Vector2::new(position.x,position.y), Vector2::new(scale.x, scale.y));
-
-            let entity = GameState::create_entity(&mut game_state)
-                .with(RenderComponent {shader_program : triangle_render!(), vertex_array_object : quad!()})
-                .with(PositionComponent {position})
-                .with(RotationComponent { rotation: Vector3::new(0.0, 0.0, 0.0) })
-                .with(ScaleComponent {scale})
-                .with(ColorComponent {color : (1.0, 1.0, 1.0, 0.0) })
-                .with(TextureMixComponent { textures : vec!
-                [texture!("src/engine/src/renderer/textures/container.jpg",0, gl::TEXTURE0, String::from("Texture1")),
-                 texture!("src/engine/src/renderer/textures/awesomeface.png",1, gl::TEXTURE1, String::from("Texture2"))],
-                    opacity: 0.0})
-                .with(TextureUpdateComponent {opacity_change : 0.0 })
-                .with(VelocityComponent {velocity : Vector3::new(0.0, 0.0, 0.0)})
-                .with(BoxCollider2DComponent {position: Vector2::new(position.x, position.y), size : Vector2::new(scale.x * 2.0, scale.y * 2.0), corners})
-                .build();
-        }
-        
         // Cycles through all events stored in this queue and executes them.
         while let Some(mut e) = one_time_events.pop_front() {
             e();
@@ -184,33 +345,25 @@ pub fn run() -> Result<(), Error> {
         }
 
         // SYSTEMS
-        unsafe {
+        schedule.run(&mut game_state)?;
 
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+        // End of rendering code.
+        let swap_buffers = ProfileScope::start("swap_buffers");
 
-            //println!("Texture");
-            texture_update_system::TextureUpdateSystem::run(&mut game_state)?;
+        window.on_update();
 
-            //println!("Selection");
-            selection_system::SelectionSystem::run((&mut game_state))?;
+        swap_buffers.end(&mut game_state);
 
-            //println!("Position");
-            position_update_system::PositionUpdateSystem::run(&mut game_state)?;
+        game_state.resource_mut::<Profiler>().end_frame();
 
-            //println!("Render");
-            render_system::RenderSystem::run(
-                (game_state.get_map::<RenderComponent>(),
-                         game_state.get_map::<PositionComponent>(),
-                         game_state.get_map::<ColorComponent>(),
-                         game_state.get_map::<TextureMixComponent>(),
-                         game_state.get_map::<ScaleComponent>(),
-                         game_state.get::<OrthographicCameraComponent>(&m_camera).unwrap(),
-                         game_state.get_map::<RotationComponent>()))?;
+        // Caps the frame rate, sleeping for whatever is left of the frame.
+        if let Some(remaining) = FRAME_BUDGET.checked_sub(game_state.resource::<Time>().frame_time()) {
+            ::std::thread::sleep(remaining);
         }
-        // End of rendering code.
-        window.on_update();
+    }
 
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 144));
+    if let Some(recording) = recording {
+        recording.finish(&game_state);
     }
 
     unsafe {
@@ -222,11 +375,10 @@ pub fn run() -> Result<(), Error> {
     Ok(())
 }
 
+/// Clears the screen and draws every renderable entity.
+fn render(game_state : &mut GameState) -> Result<(), Error> {
 
+    unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
 
-
-
-
-
-
-
+    render_system::RenderSystem::run(game_state)
+}
@@ -0,0 +1,76 @@
+//! CHANGE DETECTION
+//! Every component value stores the tick it was added on and the tick it was last changed on. The
+//! game state's change tick moves forward before each system runs, and each system remembers the
+//! tick it last ran on. Anything stamped with a later tick happened since the system last looked.
+//!
+//! Queries can filter on this with Added<T> and Changed<T>, and GameState::removed lists entities
+//! which lost a component. Mutable query results are wrapped in Mut, which only stamps the component
+//! as changed when it is actually written to.
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use crate::generational_index::generational_index::ComponentTicks;
+
+/// A mutable component handed out by a query. Reading it changes nothing; writing through it marks
+/// the component as changed.
+pub struct Mut<'a, T> {
+
+    pub(crate) value : &'a mut T,
+    pub(crate) ticks : &'a mut ComponentTicks,
+    pub(crate) tick : u64
+}
+
+impl<'a, T> Mut<'a, T> {
+
+    /// True if the component was added since the system last ran.
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_added(last_run_tick())
+    }
+
+    /// True if the component was added or changed since the system last ran.
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(last_run_tick())
+    }
+}
+
+impl<'a, T> Deref for Mut<'a, T> {
+
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T> DerefMut for Mut<'a, T> {
+
+    fn deref_mut(&mut self) -> &mut T {
+
+        self.ticks.changed = self.tick;
+        self.value
+    }
+}
+
+/// Query filter - matches entities whose T was added since the system last ran.
+pub struct Added<T>(PhantomData<T>);
+
+/// Query filter - matches entities whose T was added or changed since the system last ran.
+pub struct Changed<T>(PhantomData<T>);
+
+thread_local! {
+    static LAST_RUN : Cell<u64> = const { Cell::new(0) };
+}
+
+/// The tick the running system last ran on. Zero outside of a schedule, which means every recorded
+/// change counts.
+pub fn last_run_tick() -> u64 {
+
+    LAST_RUN.with(|tick| tick.get())
+}
+
+/// Set by the schedule before each system runs.
+pub fn set_last_run_tick(tick : u64) {
+
+    LAST_RUN.with(|last_run| last_run.set(tick));
+}
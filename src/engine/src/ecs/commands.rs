@@ -0,0 +1,146 @@
+//! COMMANDS
+//! A queue of world changes (spawning, despawning, adding and removing components) recorded while
+//! systems are running and applied later, once nothing is iterating over the world. The schedule
+//! applies the queue after every system, so a system sees the changes made by any system which ran
+//! before it.
+//!
+//! Commands are applied in the order they were recorded. Commands aimed at an entity which has been
+//! despawned in the meantime are dropped.
+
+use crate::ecs::Component;
+use crate::game_state::{GameState, Entity};
+use failure::Error;
+use serde_json::Value;
+
+pub type Command = Box<dyn FnOnce(&mut GameState) -> Result<(), Error>>;
+
+type InsertCommand = Box<dyn FnOnce(&mut GameState, &Entity)>;
+
+pub struct Commands {
+
+    queue : Vec<Command>
+}
+
+impl Default for Commands {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Commands {
+
+    pub fn new() -> Commands {
+
+        Commands { queue : Vec::new() }
+    }
+
+    /// Records an arbitrary change to the world.
+    pub fn add<F>(&mut self, command : F) where F : FnOnce(&mut GameState) -> Result<(), Error> + 'static {
+
+        self.queue.push(Box::new(command));
+    }
+
+    /// Starts recording a new entity. Nothing is queued until build is called.
+    pub fn spawn(&mut self) -> SpawnCommand<'_> {
+
+        SpawnCommand { commands : self, components : Vec::new() }
+    }
+
+    pub fn spawn_prefab(&mut self, name : &str, overrides : Value) {
+
+        let name = String::from(name);
+
+        self.add(move |state| state.spawn_prefab(&name, overrides).map(|_| ()));
+    }
+
+    pub fn despawn(&mut self, entity : Entity) {
+
+        self.add(move |state| { state.despawn(&entity); Ok(()) });
+    }
+
+    pub fn despawn_recursive(&mut self, entity : Entity) {
+
+        self.add(move |state| { state.despawn_recursive(&entity); Ok(()) });
+    }
+
+    /// Adds a component to the entity, replacing any component of the same type.
+    pub fn insert<T : Component>(&mut self, entity : Entity, component : T) {
+
+        self.add(move |state| { state.add_component_to(component, &entity); Ok(()) });
+    }
+
+    pub fn remove<T : Component>(&mut self, entity : Entity) {
+
+        self.add(move |state| { state.remove_component::<T>(&entity); Ok(()) });
+    }
+
+    /// Takes every recorded command, leaving the queue empty.
+    pub fn take(&mut self) -> Vec<Command> {
+
+        std::mem::take(&mut self.queue)
+    }
+
+    pub fn is_empty(&self) -> bool {
+
+        self.queue.is_empty()
+    }
+}
+
+/// Records the components of an entity which will be spawned when the commands are applied.
+pub struct SpawnCommand<'a> {
+
+    commands : &'a mut Commands,
+    components : Vec<InsertCommand>
+}
+
+impl<'a> SpawnCommand<'a> {
+
+    pub fn with<T : Component>(mut self, component : T) -> Self {
+
+        self.components.push(Box::new(move |state, entity| { state.add_component_to(component, entity); }));
+
+        self
+    }
+
+    /// Queues the spawn.
+    pub fn build(self) {
+
+        let components = self.components;
+
+        self.commands.add(move |state| {
+
+            let entity = GameState::create_entity(state).build();
+
+            for component in components {
+                component(state, &entity);
+            }
+
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use failure::err_msg;
+
+    #[test]
+    fn commands_after_a_failing_one_are_still_applied() {
+
+        let mut state = GameState::create_initial_state();
+
+        state.commands.add(|_| Err(err_msg("first")));
+        state.commands.spawn().build();
+        state.commands.add(|_| Err(err_msg("second")));
+        state.commands.spawn().build();
+
+        let error = state.apply_commands().unwrap_err().to_string();
+
+        assert!(error.contains("first") && error.contains("second"), "{}", error);
+        assert_eq!(state.entity_count(), 2);
+        assert!(state.commands.is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+//! HOOKS
+//! The component hooks the engine relies on, registered when the state is created. Hooks run
+//! wherever the change is made - for changes recorded through commands that is when the commands
+//! are applied, once the system which recorded them has finished.
+//! SEE: GameState::on_add, GameState::on_remove
+
+use crate::ecs::{RenderComponent, TextureMixComponent, NameComponent};
+use crate::game_state::{GameState, Entity};
+use crate::renderer;
+
+pub fn register_engine_hooks(state : &mut GameState) {
+
+    state.on_remove::<RenderComponent>(delete_render_objects);
+    state.on_remove::<TextureMixComponent>(delete_textures);
+
+    state.on_add::<NameComponent>(index_name);
+    state.on_remove::<NameComponent>(unindex_name);
+}
+
+/// Frees the shader program and vertex array built for the component.
+fn delete_render_objects(state : &mut GameState, entity : &Entity) {
+
+    if !renderer::gl_loaded() {
+        return
+    }
+
+    if let Some(render) = state.get::<RenderComponent>(entity) {
+
+        unsafe {
+            gl::DeleteProgram(render.shader_program);
+            gl::DeleteVertexArrays(1, &render.vertex_array_object);
+        }
+    }
+}
+
+fn delete_textures(state : &mut GameState, entity : &Entity) {
+
+    if !renderer::gl_loaded() {
+        return
+    }
+
+    if let Some(texture_mix) = state.get::<TextureMixComponent>(entity) {
+
+        let texture_ids = texture_mix.textures.iter().map(|texture| texture.texture_id).collect::<Vec<gl::types::GLuint>>();
+
+        unsafe { gl::DeleteTextures(texture_ids.len() as i32, texture_ids.as_ptr()); }
+    }
+}
+
+fn index_name(state : &mut GameState, entity : &Entity) {
+
+    if let Some(name) = state.get::<NameComponent>(entity).map(|name| name.name.clone()) {
+        state.names.insert(name, *entity);
+    }
+}
+
+fn unindex_name(state : &mut GameState, entity : &Entity) {
+
+    if let Some(name) = state.get::<NameComponent>(entity).map(|name| name.name.clone()) {
+
+        if state.names.get(&name) == Some(entity) {
+            state.names.remove(&name);
+        }
+    }
+}
@@ -1,13 +1,23 @@
 use std::any::Any;
 use nalgebra::{Vector3, Matrix4, Vector2};
+use crate::game_state::Entity;
+use failure::{Error, err_msg};
+use crate::ecs::storage::StorageType;
 
 pub mod system;
+pub mod query;
+pub mod registry;
+pub mod schedule;
+pub mod change_detection;
+pub mod commands;
+pub mod snapshot;
+pub mod reflect;
+pub mod hooks;
+pub mod storage;
 pub mod render_system;
 pub mod texture_update_system;
 pub mod position_update_system;
-pub mod check_mouse_collision_system;
-pub mod selection_system;
-pub mod look_at_position_system;
+pub mod transform_propagation_system;
 
 #[macro_export]
 // Macro for creating a key typed event.
@@ -18,7 +28,8 @@ macro_rules! texture { ($path:expr, $number:expr, $enum:expr, $name:expr) => {{
 
         let mut texture_id : gl::types::GLuint = 0;
 
-        unsafe {
+        // Without a GL context the texture is left empty.
+        if $crate::renderer::gl_loaded() { unsafe {
 
             gl::GenTextures(1, &mut texture_id);
             gl::BindTexture(gl::TEXTURE_2D, texture_id);
@@ -33,36 +44,33 @@ macro_rules! texture { ($path:expr, $number:expr, $enum:expr, $name:expr) => {{
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
 
             gl::GenerateMipmap(gl::TEXTURE_2D);
-        }
+        }}
 
-        Texture { uniform_name: $name, texture_id, number: $number, active_texture_enum: $enum }
+        Texture { path: String::from($path), uniform_name: $name, texture_id, number: $number, active_texture_enum: $enum }
     }};
 }
 
-pub enum Components {
-
-    Position, Velocity, Scale, Rotation,
-    Color, Render, Texture, TextureUpdate, Orthographic,
-    BoxCollider2D,
-    Selected,
-    NA
-}
-
-/// START OF TRANSFORM COMPONENTS ----------------------------------------------------------------->
+// START OF TRANSFORM COMPONENTS ----------------------------------------------------------------->
 
 /// POSITION
 /// PositionComponent - Used to store the Entity's position. Currently represented as a
 /// Vector3
+#[derive(Clone)]
 pub struct PositionComponent {
 
     pub position : Vector3<f32>
 }
 
-impl Component for PositionComponent {}
+impl Component for PositionComponent {
+
+    const STORAGE : StorageType = StorageType::Table;
+}
 
 /// VELOCITY
-/// Used to store the velocity of the entity. Currently adds velocity to position every frame
+/// Used to store the velocity of the entity in units per second. Added to position every fixed step,
+/// and slowly damped back to zero.
 /// SEE: position_update_system
+#[derive(Clone)]
 pub struct VelocityComponent {
 
     pub velocity : Vector3<f32>
@@ -70,16 +78,36 @@ pub struct VelocityComponent {
 
 impl Component for VelocityComponent {}
 
+/// PREVIOUS POSITION
+/// Where a moving entity was before the latest fixed step. Rendering draws the entity between this
+/// and its current position, so movement stays smooth when the frame rate and step rate differ.
+/// Added to anything with a position and velocity.
+/// SEE: position_update_system
+#[derive(Clone)]
+pub struct PreviousPositionComponent {
+
+    pub position : Vector3<f32>
+}
+
+impl Component for PreviousPositionComponent {
+
+    const STORAGE : StorageType = StorageType::Table;
+}
+
 /// ROTATION
 /// Stores current object rotation.
-
+#[derive(Clone)]
 pub struct RotationComponent {
 
     pub rotation : Vector3<f32>
 }
 
-impl Component for RotationComponent {}
+impl Component for RotationComponent {
 
+    const STORAGE : StorageType = StorageType::Table;
+}
+
+#[derive(Clone)]
 pub struct RotationUpdateComponent {
 
     pub axis : nalgebra::Vector3<f32>,
@@ -88,38 +116,109 @@ pub struct RotationUpdateComponent {
 
 impl Component for RotationUpdateComponent {}
 
+#[derive(Clone)]
 pub struct ScaleComponent {
     pub scale : Vector3<f32>
 }
 
-impl Component for ScaleComponent { }
+impl Component for ScaleComponent {
+
+    const STORAGE : StorageType = StorageType::Table;
+}
+
+/// HIERARCHY
+/// An entity with a ParentComponent has its position, rotation and scale treated as relative to its
+/// parent. Use GameState::set_parent rather than adding these directly, so both sides stay in sync.
+#[derive(Clone)]
+pub struct ParentComponent {
+
+    pub parent : Entity
+}
+
+impl Component for ParentComponent {}
+
+#[derive(Clone)]
+pub struct ChildrenComponent {
+
+    pub children : Vec<Entity>
+}
+
+impl Component for ChildrenComponent {}
+
+/// GLOBAL TRANSFORM
+/// The entity's model matrix in world space. Computed every frame from the local transform
+/// components by the transform propagation system - never set by hand.
+/// SEE: transform_propagation_system
+#[derive(Clone)]
+pub struct GlobalTransformComponent {
+
+    pub matrix : Matrix4<f32>
+}
+
+impl Component for GlobalTransformComponent {
 
-/// END OF TRANFORM COMPONENTS -------------------------------------------------------------------->
+    const STORAGE : StorageType = StorageType::Table;
+}
+
+// END OF TRANFORM COMPONENTS -------------------------------------------------------------------->
 
 /// COLOR
 /// Need to abstract color object.
+#[derive(Clone)]
 pub struct ColorComponent {
 
     pub color : (f32, f32, f32, f32)
 }
 
-impl Component for ColorComponent {}
+impl Component for ColorComponent {
+
+    const STORAGE : StorageType = StorageType::Table;
+}
 
 /// RENDERER
 /// Stores basic shader and renderer information.
 /// Uses position and velocity to update itself.
-
+/// shader and mesh name the assets the GL handles were built from, so the component can be saved
+/// and rebuilt.
 pub struct RenderComponent {
 
     pub shader_program : gl::types::GLuint,
-    pub vertex_array_object : gl::types::GLuint
+    pub vertex_array_object : gl::types::GLuint,
+    pub shader : String,
+    pub mesh : String
+}
+
+impl Component for RenderComponent {
+
+    const STORAGE : StorageType = StorageType::Table;
 }
 
-impl Component for RenderComponent {}
+impl RenderComponent {
+
+    /// Builds the GL handles for a named shader and mesh. Currently only the "triangle" shader and
+    /// the "quad" mesh exist. Without a GL context the handles are left empty.
+    pub fn from_assets(shader : &str, mesh : &str) -> Result<RenderComponent, Error> {
+
+        let gl = crate::renderer::gl_loaded();
+
+        let shader_program = match shader {
+            "triangle" if gl => triangle_render!(),
+            "triangle" => 0,
+            _ => return Err(err_msg(format!("Unknown shader: {}", shader)))
+        };
+
+        let vertex_array_object = match mesh {
+            "quad" if gl => quad!(),
+            "quad" => 0,
+            _ => return Err(err_msg(format!("Unknown mesh: {}", mesh)))
+        };
+
+        Ok(RenderComponent { shader_program, vertex_array_object, shader : String::from(shader), mesh : String::from(mesh) })
+    }
+}
 
 /// TEXTURES
 /// Stores a list of textures which can be overlaid on top of each other.
-
 pub struct TextureMixComponent {
 
     pub textures : Vec<Texture>,
@@ -128,11 +227,26 @@ pub struct TextureMixComponent {
 
 impl Component for TextureMixComponent {}
 
-/// TEXTURE
-/// A single texture object. Stores all texture data.
+impl TextureMixComponent {
 
+    /// Loads a fresh copy of every texture from its path, into the same texture units.
+    pub fn reload(&self) -> Result<TextureMixComponent, Error> {
+
+        let mut textures = Vec::with_capacity(self.textures.len());
+
+        for texture in self.textures.iter() {
+            textures.push(texture!(texture.path.as_str(), texture.number, texture.active_texture_enum, texture.uniform_name.clone()));
+        }
+
+        Ok(TextureMixComponent { textures, opacity : self.opacity })
+    }
+}
+
+/// TEXTURE
+/// A single texture object. Stores all texture data, along with the path the image was loaded from.
 pub struct Texture {
 
+    pub path : String,
     pub uniform_name : String,
     pub texture_id : gl::types::GLuint,
     pub number : i32,
@@ -140,7 +254,7 @@ pub struct Texture {
 }
 
 /// Stores details of textures which may or may not change each frame.
-
+#[derive(Clone)]
 pub struct TextureUpdateComponent {
 
     pub opacity_change : gl::types::GLfloat
@@ -148,8 +262,9 @@ pub struct TextureUpdateComponent {
 
 impl Component for TextureUpdateComponent {}
 
-///
-
+/// ORTHOGRAPHIC CAMERA
+/// The view and projection everything is drawn with. Kept as a resource rather than on an entity.
+#[derive(Clone)]
 pub struct OrthographicCameraComponent {
 
     pub dimensions : Vector2<f32>,
@@ -159,8 +274,27 @@ pub struct OrthographicCameraComponent {
 
 impl Component for OrthographicCameraComponent {}
 
-///
+impl OrthographicCameraComponent {
+
+    /// A camera at the given position looking at a screen of the given size, centred on the origin.
+    pub fn new(dimensions : Vector2<f32>, position : Vector3<f32>) -> OrthographicCameraComponent {
 
+        OrthographicCameraComponent {
+            dimensions,
+            view : Matrix4::new_translation(&position),
+            projection : Matrix4::new_orthographic(
+                -(dimensions.x / 2.0),
+                dimensions.x / 2.0,
+                -(dimensions.y / 2.0),
+                dimensions.y / 2.0, 1.0, -1.0)
+        }
+    }
+}
+
+/// BOX COLLIDER
+/// A rectangle around the entity, e.g. for picking. The corners are kept rotated to match the
+/// entity's rotation.
+#[derive(Clone)]
 pub struct BoxCollider2DComponent {
 
     pub size : Vector2<f32>,
@@ -170,23 +304,36 @@ pub struct BoxCollider2DComponent {
 
 impl Component for BoxCollider2DComponent {}
 
-pub struct SelectedComponent {
+/// NAME
+/// A unique name which the entity can be looked up by. Set with GameState::set_name so the name index
+/// stays up to date.
+/// SEE: GameState::find_by_name
+#[derive(Clone)]
+pub struct NameComponent {
 
-    pub selected_color : (f32, f32, f32, f32),
-    pub origin_color : (f32, f32, f32, f32),
-    pub cursor_offset : Vector2<f32>
+    pub name : String
 }
 
-impl Component for SelectedComponent {}
+impl Component for NameComponent {}
 
-pub struct LookAtPositionComponent {
+/// TAGS
+/// Labels such as "Player" or "Enemy" which group entities together. Unlike names, any number of
+/// entities can share a tag.
+/// SEE: GameState::entities_with_tag
+#[derive(Clone)]
+pub struct TagsComponent {
 
-    pub focus_point: Vector2<f32>
+    pub tags : Vec<String>
 }
 
-impl Component for LookAtPositionComponent {}
+impl Component for TagsComponent {}
+
+/// Anything which can be attached to an entity. STORAGE picks how components of the type are stored.
+/// SEE: storage
+pub trait Component: Any + Sized {
 
-pub trait Component: Any + Sized {}
+    const STORAGE : StorageType = StorageType::Sparse;
+}
 
 
 
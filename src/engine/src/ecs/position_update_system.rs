@@ -1,10 +1,15 @@
 use crate::ecs::system::System;
-use crate::ecs::{PositionComponent, VelocityComponent, BoxCollider2DComponent, RotationComponent, RotationUpdateComponent};
+use crate::ecs::{PositionComponent, VelocityComponent, PreviousPositionComponent, BoxCollider2DComponent};
+use crate::ecs::query::With;
 use failure::Error;
-use crate::generational_index::generational_index::{GenerationalIndex, GenerationalIndexArray};
-use crate::game_state::GameState;
-use nalgebra::{Vector3, Vector2};
+use crate::game_state::{GameState, Entity};
+use crate::time::Time;
+use nalgebra::Vector2;
 
+/// How quickly velocity dies away, per second. Matches the old per frame damping at 144fps.
+const DAMPING : f32 = 32.0;
+
+/// Moves everything with a velocity. Runs in FixedUpdate, so movement is the same at any frame rate.
 pub struct PositionUpdateSystem;
 
 impl<'a> System<'a> for PositionUpdateSystem {
@@ -13,53 +18,55 @@ impl<'a> System<'a> for PositionUpdateSystem {
 
     fn run(input: Self::SystemInput) -> Result<(), Error> {
 
-        let size : usize = input.get_map::<VelocityComponent>().entries.len();
-
-        for index in 0..size {
+        let delta = input.resource::<Time>().fixed_delta;
 
-            let mut generation = 0;
+        let damping = (-DAMPING * delta).exp();
 
-            let mut idx : GenerationalIndex = GenerationalIndex {index: 0, generation: 0};
+        let query = input.query::<(&mut VelocityComponent, &mut PositionComponent, Option<&mut BoxCollider2DComponent>)>();
 
-            let mut velocity_change : Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+        for (mut velocity, mut position, collider) in query {
 
-            {
-                if let Some(velocity) = input.get_map_mut::<VelocityComponent>().entries[index].as_mut() {
+            let movement = velocity.velocity * delta;
 
-                    idx = velocity.owned_entity;
+            position.position += movement;
 
-                    let current_velocity = velocity.value.velocity;
+            if let Some(mut collider) = collider {
 
-                        let velocity_length = nalgebra::Vector3::magnitude(&current_velocity);
+                collider.position += Vector2::new(movement.x, movement.y);
+            }
 
-                    if velocity_length > 0.0 {
+            velocity.velocity *= damping;
+        }
+        Ok(())
+    }
+}
 
-                        let x = if f32::is_sign_positive(current_velocity.x) { current_velocity.x } else { -current_velocity.x };
-                        let y = if f32::is_sign_positive(current_velocity.y) { current_velocity.y } else { -current_velocity.y };
+/// Keeps each moving entity's position from before the fixed step, for render interpolation. Runs at
+/// the start of FixedUpdate.
+pub struct PreviousPositionSystem;
 
-                        velocity_change = Vector3::normalize(&current_velocity);
+impl<'a> System<'a> for PreviousPositionSystem {
 
-                        velocity_change = Vector3::new( velocity_change.x * x, velocity_change.y * y, 0.0);
-                    }
+    type SystemInput = &'a mut GameState;
 
-                    velocity.value.velocity -= Vector3::new(current_velocity.x * 0.2, current_velocity.y * 0.2, 0.0);
-                }
-            }
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
 
-            {
-                let positions = &mut input.get_mut::<PositionComponent>(&idx).unwrap();
+        let query = input.query::<(Entity, &PositionComponent, Option<&mut PreviousPositionComponent>, With<VelocityComponent>)>();
 
-                positions.position += velocity_change;
-                //println!("Player position (x: {} y: {})", positions.position.x, positions.position.y);
-            }
+        let mut missing = Vec::new();
 
-            {
-                if let Some(collider) = &mut input.get_mut::<BoxCollider2DComponent>(&idx) {
+        for (entity, position, previous, _) in query {
 
-                    collider.position += Vector2::new(velocity_change.x, velocity_change.y);
-                }
+            match previous {
+                Some(mut previous) => previous.position = position.position,
+                None => missing.push((entity, position.position))
             }
         }
+
+        for (entity, position) in missing {
+            input.commands.insert(entity, PreviousPositionComponent { position });
+        }
+
         Ok(())
     }
 }
@@ -68,11 +75,11 @@ pub struct RotationUpdateSystem();
 
 impl<'a> System<'a> for RotationUpdateSystem {
 
-    type SystemInput = (&'a mut GameState);
+    type SystemInput = &'a mut GameState;
 
-    fn run(input: Self::SystemInput) -> Result<(), Error> {
+    fn run(_input: Self::SystemInput) -> Result<(), Error> {
 
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,554 @@
+//! QUERIES
+//! Allows systems to iterate over every entity which has a given set of components, e.g:
+//!
+//! state.query::<(&PositionComponent, &mut VelocityComponent, Option<&ScaleComponent>)>()
+//!
+//! Entities which are missing a required component are skipped rather than unwrapped. Mutable
+//! components come back wrapped in Mut, which records when they are written to.
+
+use std::any::{TypeId, type_name};
+use std::marker::PhantomData;
+use crate::ecs::Component;
+use crate::ecs::change_detection::{Mut, Added, Changed, last_run_tick};
+use crate::ecs::storage::{StorageType, Tables};
+use crate::game_state::{GameState, Entity, EntityMap};
+use crate::generational_index::generational_index::{ComponentTicks, GenerationalIndexAllocator};
+
+/// Records which component a query element touches, and whether it does so mutably.
+#[derive(Clone, Copy)]
+pub struct ComponentAccess {
+
+    pub type_id : TypeId,
+    pub type_name : &'static str,
+    pub mutable : bool
+}
+
+impl ComponentAccess {
+
+    pub fn read<T : 'static>() -> ComponentAccess {
+        ComponentAccess { type_id : TypeId::of::<T>(), type_name : type_name::<T>(), mutable : false }
+    }
+
+    pub fn write<T : 'static>() -> ComponentAccess {
+        ComponentAccess { type_id : TypeId::of::<T>(), type_name : type_name::<T>(), mutable : true }
+    }
+}
+
+/// A single element of a query. Implemented for &T, &mut T, Option<&T>, Option<&mut T>, Entity,
+/// the With/Without/Added/Changed filters and tuples of all of these.
+///
+/// Each element looks up the storage of the component it touches once, when the query is made, and
+/// keeps raw pointers to it. Fetching goes through those pointers alone, so a mutable item never
+/// shares a borrow with the rest of the game state or with the other items of the same query.
+pub trait Fetch<'a> {
+
+    type Item;
+
+    /// Whatever the element looks up when the query is made, e.g. pointers to its component's
+    /// storage.
+    type State;
+
+    /// Pushes the components this element reads or writes. Filters push nothing.
+    fn access(access : &mut Vec<ComponentAccess>);
+
+    /// Looks up the element's storage.
+    ///
+    /// # Safety
+    /// The state has to be valid, and nothing else may borrow it while this runs.
+    unsafe fn init(state : *mut GameState) -> Self::State;
+
+    /// Pushes the table components an entity must have to match, so tables without them can be
+    /// skipped whole.
+    fn required_tables(_types : &mut Vec<TypeId>) {}
+
+    /// The entities of the smallest sparse set an entity must be in to match, if there is one.
+    ///
+    /// # Safety
+    /// The storage the state points at has to outlive 'a, and mustn't change while the slice is used.
+    unsafe fn candidates(_state : &Self::State) -> Option<&'a [Entity]> {
+        None
+    }
+
+    /// Fetches the element for a single live entity, given the table and row holding its table
+    /// components, or None if the entity does not match.
+    ///
+    /// # Safety
+    /// The storage the state points at has to still be alive, and no other live reference may alias
+    /// a mutable fetch.
+    unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item>;
+}
+
+/// Filter which only matches entities that have a T component.
+pub struct With<T>(PhantomData<T>);
+
+/// Filter which only matches entities that do NOT have a T component.
+pub struct Without<T>(PhantomData<T>);
+
+/// Pointers to wherever a component type is stored, looked up once per query.
+pub enum ComponentStorage<T> {
+
+    /// The type's sparse set, along with the start of its values and ticks.
+    Sparse(*const EntityMap<T>, *mut T, *mut ComponentTicks),
+
+    /// The start of the type's column in each table, None for tables without one.
+    Table(Vec<Option<(*mut T, *mut ComponentTicks)>>),
+
+    /// No entity has ever had the component.
+    Missing
+}
+
+impl<T : Component> ComponentStorage<T> {
+
+    unsafe fn new(state : *mut GameState) -> ComponentStorage<T> {
+
+        match T::STORAGE {
+            StorageType::Sparse => match (*state).components.get_mut::<EntityMap<T>>() {
+                Some(map) => {
+                    let (values, ticks) = map.as_mut_ptrs();
+                    ComponentStorage::Sparse(map, values, ticks)
+                },
+                None => ComponentStorage::Missing
+            },
+            StorageType::Table => ComponentStorage::Table((*state).tables.column_ptrs::<T>())
+        }
+    }
+
+    /// Pushes the type if it lives in tables.
+    fn required_tables(types : &mut Vec<TypeId>) {
+
+        if T::STORAGE == StorageType::Table {
+            types.push(TypeId::of::<T>());
+        }
+    }
+
+    /// Every entity with the component, if it is sparse. Nothing matches a missing component.
+    unsafe fn candidates<'a>(&self) -> Option<&'a [Entity]> {
+
+        match self {
+            ComponentStorage::Sparse(map, _, _) => Some((**map).entities()),
+            ComponentStorage::Table(_) => None,
+            ComponentStorage::Missing => Some(&[])
+        }
+    }
+
+    /// Pointers to the entity's value and ticks, if it has the component.
+    unsafe fn slot(&self, entity : &Entity, location : Option<(usize, usize)>) -> Option<(*mut T, *mut ComponentTicks)> {
+
+        match self {
+            ComponentStorage::Sparse(map, values, ticks) => {
+                let dense_index = (**map).dense_index(entity)?;
+                Some((values.add(dense_index), ticks.add(dense_index)))
+            },
+            ComponentStorage::Table(columns) => {
+                let (table, row) = location?;
+                let (values, ticks) = (*columns.get(table)?)?;
+                Some((values.add(row), ticks.add(row)))
+            },
+            ComponentStorage::Missing => None
+        }
+    }
+}
+
+impl<'a, T : Component> Fetch<'a> for &'a T {
+
+    type Item = &'a T;
+    type State = ComponentStorage<T>;
+
+    fn access(access : &mut Vec<ComponentAccess>) {
+        access.push(ComponentAccess::read::<T>());
+    }
+
+    unsafe fn init(state : *mut GameState) -> Self::State {
+        ComponentStorage::new(state)
+    }
+
+    fn required_tables(types : &mut Vec<TypeId>) {
+        ComponentStorage::<T>::required_tables(types);
+    }
+
+    unsafe fn candidates(state : &Self::State) -> Option<&'a [Entity]> {
+        state.candidates()
+    }
+
+    unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+        let (value, _) = state.slot(entity, location)?;
+
+        Some(&*value)
+    }
+}
+
+impl<'a, T : Component> Fetch<'a> for &'a mut T {
+
+    type Item = Mut<'a, T>;
+
+    /// The storage, plus the tick writes are stamped with.
+    type State = (ComponentStorage<T>, u64);
+
+    fn access(access : &mut Vec<ComponentAccess>) {
+        access.push(ComponentAccess::write::<T>());
+    }
+
+    unsafe fn init(state : *mut GameState) -> Self::State {
+        (ComponentStorage::new(state), (*state).change_tick)
+    }
+
+    fn required_tables(types : &mut Vec<TypeId>) {
+        ComponentStorage::<T>::required_tables(types);
+    }
+
+    unsafe fn candidates(state : &Self::State) -> Option<&'a [Entity]> {
+        state.0.candidates()
+    }
+
+    unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+        let (value, ticks) = state.0.slot(entity, location)?;
+
+        Some(Mut { value : &mut *value, ticks : &mut *ticks, tick : state.1 })
+    }
+}
+
+impl<'a, T : Component> Fetch<'a> for Option<&'a T> {
+
+    type Item = Option<&'a T>;
+    type State = ComponentStorage<T>;
+
+    fn access(access : &mut Vec<ComponentAccess>) {
+        access.push(ComponentAccess::read::<T>());
+    }
+
+    unsafe fn init(state : *mut GameState) -> Self::State {
+        ComponentStorage::new(state)
+    }
+
+    unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+        Some(<&'a T as Fetch<'a>>::fetch(state, entity, location))
+    }
+}
+
+impl<'a, T : Component> Fetch<'a> for Option<&'a mut T> {
+
+    type Item = Option<Mut<'a, T>>;
+    type State = (ComponentStorage<T>, u64);
+
+    fn access(access : &mut Vec<ComponentAccess>) {
+        access.push(ComponentAccess::write::<T>());
+    }
+
+    unsafe fn init(state : *mut GameState) -> Self::State {
+        (ComponentStorage::new(state), (*state).change_tick)
+    }
+
+    unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+        Some(<&'a mut T as Fetch<'a>>::fetch(state, entity, location))
+    }
+}
+
+impl<'a> Fetch<'a> for Entity {
+
+    type Item = Entity;
+    type State = ();
+
+    fn access(_access : &mut Vec<ComponentAccess>) {}
+
+    unsafe fn init(_state : *mut GameState) -> Self::State {}
+
+    unsafe fn fetch(_state : &Self::State, entity : &Entity, _location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+        Some(*entity)
+    }
+}
+
+impl<'a, T : Component> Fetch<'a> for With<T> {
+
+    type Item = ();
+    type State = ComponentStorage<T>;
+
+    fn access(_access : &mut Vec<ComponentAccess>) {}
+
+    unsafe fn init(state : *mut GameState) -> Self::State {
+        ComponentStorage::new(state)
+    }
+
+    fn required_tables(types : &mut Vec<TypeId>) {
+        ComponentStorage::<T>::required_tables(types);
+    }
+
+    unsafe fn candidates(state : &Self::State) -> Option<&'a [Entity]> {
+        state.candidates()
+    }
+
+    unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+        match state.slot(entity, location) {
+            Some(_) => Some(()),
+            None => None
+        }
+    }
+}
+
+impl<'a, T : Component> Fetch<'a> for Without<T> {
+
+    type Item = ();
+    type State = ComponentStorage<T>;
+
+    fn access(_access : &mut Vec<ComponentAccess>) {}
+
+    unsafe fn init(state : *mut GameState) -> Self::State {
+        ComponentStorage::new(state)
+    }
+
+    unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+        match state.slot(entity, location) {
+            Some(_) => None,
+            None => Some(())
+        }
+    }
+}
+
+impl<'a, T : Component> Fetch<'a> for Added<T> {
+
+    type Item = ();
+    type State = ComponentStorage<T>;
+
+    fn access(_access : &mut Vec<ComponentAccess>) {}
+
+    unsafe fn init(state : *mut GameState) -> Self::State {
+        ComponentStorage::new(state)
+    }
+
+    fn required_tables(types : &mut Vec<TypeId>) {
+        ComponentStorage::<T>::required_tables(types);
+    }
+
+    unsafe fn candidates(state : &Self::State) -> Option<&'a [Entity]> {
+        state.candidates()
+    }
+
+    unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+        match state.slot(entity, location) {
+            Some((_, ticks)) if (*ticks).is_added(last_run_tick()) => Some(()),
+            _ => None
+        }
+    }
+}
+
+impl<'a, T : Component> Fetch<'a> for Changed<T> {
+
+    type Item = ();
+    type State = ComponentStorage<T>;
+
+    fn access(_access : &mut Vec<ComponentAccess>) {}
+
+    unsafe fn init(state : *mut GameState) -> Self::State {
+        ComponentStorage::new(state)
+    }
+
+    fn required_tables(types : &mut Vec<TypeId>) {
+        ComponentStorage::<T>::required_tables(types);
+    }
+
+    unsafe fn candidates(state : &Self::State) -> Option<&'a [Entity]> {
+        state.candidates()
+    }
+
+    unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+        match state.slot(entity, location) {
+            Some((_, ticks)) if (*ticks).is_changed(last_run_tick()) => Some(()),
+            _ => None
+        }
+    }
+}
+
+// Implements Fetch for tuples of fetches. An entity only matches if every element matches.
+macro_rules! impl_fetch_tuple {
+    ($(($name:ident, $index:tt)),+) => {
+        impl<'a, $($name : Fetch<'a>),+> Fetch<'a> for ($($name,)+) {
+
+            type Item = ($($name::Item,)+);
+            type State = ($($name::State,)+);
+
+            fn access(access : &mut Vec<ComponentAccess>) {
+                $($name::access(access);)+
+            }
+
+            unsafe fn init(state : *mut GameState) -> Self::State {
+                ($($name::init(state),)+)
+            }
+
+            fn required_tables(types : &mut Vec<TypeId>) {
+                $($name::required_tables(types);)+
+            }
+
+            unsafe fn candidates(state : &Self::State) -> Option<&'a [Entity]> {
+
+                let mut smallest : Option<&'a [Entity]> = None;
+
+                $(
+                    if let Some(entities) = $name::candidates(&state.$index) {
+                        if smallest.map_or(true, |smallest| entities.len() < smallest.len()) {
+                            smallest = Some(entities);
+                        }
+                    }
+                )+
+
+                smallest
+            }
+
+            unsafe fn fetch(state : &Self::State, entity : &Entity, location : Option<(usize, usize)>) -> Option<Self::Item> {
+
+                Some(($($name::fetch(&state.$index, entity, location)?,)+))
+            }
+        }
+    };
+}
+
+impl_fetch_tuple!((A, 0));
+impl_fetch_tuple!((A, 0), (B, 1));
+impl_fetch_tuple!((A, 0), (B, 1), (C, 2));
+impl_fetch_tuple!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_fetch_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+impl_fetch_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5));
+impl_fetch_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5), (G, 6));
+impl_fetch_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5), (G, 6), (H, 7));
+
+/// Checks that a query never hands out a mutable reference to a component alongside any other
+/// reference to that same component. Panics if it does.
+pub fn validate_access<'a, Q : Fetch<'a>>() {
+
+    let mut access = Vec::new();
+
+    Q::access(&mut access);
+
+    for (index, first) in access.iter().enumerate() {
+        for second in access.iter().skip(index + 1) {
+            if first.type_id == second.type_id && (first.mutable || second.mutable) {
+                panic!("Query accesses {} mutably alongside another reference to it.", first.type_name);
+            }
+        }
+    }
+}
+
+/// Iterator returned by GameState::query. Only visits entities which could match:
+///
+/// If the query requires table components, it walks the rows of every table which has all of
+/// them, reading each column front to back.
+/// If it requires sparse components, it walks the smallest of those sparse sets instead whenever
+/// that is fewer entities than the matching tables hold.
+/// A query which requires no components at all (e.g. only Entity and Options) visits every entity.
+///
+/// Entities which have been despawned are always skipped, even if storage still holds something
+/// for them.
+pub struct Query<'a, Q : Fetch<'a>> {
+
+    fetch : Q::State,
+    tables : *const Tables,
+    allocator : *const GenerationalIndexAllocator,
+    cursor : Cursor<'a>,
+    marker : PhantomData<(&'a mut GameState, Q)>
+}
+
+/// Where a query has got to.
+enum Cursor<'a> {
+
+    /// The index into the matching tables, and the next row of that table.
+    Tables { tables : Vec<usize>, table : usize, row : usize },
+
+    Sparse { entities : &'a [Entity], index : usize },
+
+    All { entities : Vec<Entity>, index : usize }
+}
+
+impl<'a, Q : Fetch<'a>> Query<'a, Q> {
+
+    /// Basic constructor. Validates the query's component access before anything is fetched.
+    pub fn new(state : &'a mut GameState) -> Query<'a, Q> {
+
+        validate_access::<Q>();
+
+        let fetch = unsafe { Q::init(state) };
+
+        let mut required = Vec::new();
+
+        Q::required_tables(&mut required);
+
+        let tables = state.tables.tables().iter()
+            .enumerate()
+            .filter(|(_, table)| !table.is_empty() && required.iter().all(|type_id| table.has_column(type_id)))
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+
+        let rows = tables.iter().map(|table| state.tables.tables()[*table].len()).sum::<usize>();
+
+        let cursor = match unsafe { Q::candidates(&fetch) } {
+            Some(entities) if required.is_empty() || entities.len() < rows => Cursor::Sparse { entities, index : 0 },
+            _ if !required.is_empty() => Cursor::Tables { tables, table : 0, row : 0 },
+            _ => Cursor::All { entities : state.entities().collect(), index : 0 }
+        };
+
+        Query { fetch, tables : &state.tables, allocator : &state.allocator, cursor, marker : PhantomData }
+    }
+}
+
+impl<'a, Q : Fetch<'a>> Iterator for Query<'a, Q> {
+
+    type Item = Q::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        // Each entity is visited once, so mutable items handed out here never alias. Rows don't
+        // move and entities aren't despawned while the query holds the state, so neither do the
+        // storage pointers.
+        unsafe {
+            loop {
+                let (entity, location) = match &mut self.cursor {
+                    Cursor::Tables { tables, table, row } => {
+
+                        let index = *tables.get(*table)?;
+                        let entities = (*self.tables).tables()[index].entities();
+
+                        if *row >= entities.len() {
+                            *table += 1;
+                            *row = 0;
+                            continue
+                        }
+
+                        *row += 1;
+
+                        (entities[*row - 1], Some((index, *row - 1)))
+                    },
+                    Cursor::Sparse { entities, index } => {
+
+                        let entity = *entities.get(*index)?;
+
+                        *index += 1;
+
+                        (entity, (*self.tables).location(&entity))
+                    },
+                    Cursor::All { entities, index } => {
+
+                        let entity = *entities.get(*index)?;
+
+                        *index += 1;
+
+                        (entity, (*self.tables).location(&entity))
+                    }
+                };
+
+                if !(*self.allocator).is_live(&entity) {
+                    continue
+                }
+
+                if let Some(item) = Q::fetch(&self.fetch, &entity, location) {
+                    return Some(item)
+                }
+            }
+        }
+    }
+}
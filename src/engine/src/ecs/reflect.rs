@@ -0,0 +1,358 @@
+//! REFLECTION
+//! Lets inspectors, consoles and tools read and write component fields at runtime without knowing
+//! the component types. Every reflected type lists its fields and can get or set any of them by
+//! path, with values passed around as JSON, e.g:
+//!
+//! state.reflect_get(&entity, "PositionComponent.position.x")
+//! state.reflect_set(&entity, "ColorComponent.color.3", json!(0.5))
+//!
+//! A path starts with the short name of a component which has been registered with
+//! GameState::register_reflect (every engine component is registered when the state is created).
+//! Each following segment names a field, or an element for vectors and tuples. A path which stops
+//! early gets or sets everything below it at once.
+//!
+//! Sets go straight to the component and mark it as changed. Fields listed as read_only can be read
+//! but never set: GL handles, which would leak the objects they replace, and names and hierarchy
+//! links, which have to go through GameState::set_name and GameState::set_parent to keep their
+//! indices up to date.
+
+use crate::ecs::*;
+use crate::game_state::{GameState, Entity};
+use failure::{Error, err_msg};
+use serde_json::Value;
+use std::any::type_name;
+
+pub trait Reflect {
+
+    /// The name and type of every field, in declaration order.
+    fn fields(&self) -> Vec<FieldInfo>;
+
+    fn get_path(&self, path : &[&str]) -> Result<Value, Error>;
+
+    fn set_path(&mut self, path : &[&str], value : Value) -> Result<(), Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct FieldInfo {
+
+    pub name : &'static str,
+    pub type_name : &'static str,
+    pub read_only : bool
+}
+
+/// Implements Reflect for plain values, which are converted to and from JSON with serde.
+macro_rules! reflect_value { ($($value:ty),*) => {
+    $(
+        impl Reflect for $value {
+
+            fn fields(&self) -> Vec<FieldInfo> {
+                Vec::new()
+            }
+
+            fn get_path(&self, path : &[&str]) -> Result<Value, Error> {
+
+                match path.first() {
+                    None => Ok(serde_json::to_value(self)?),
+                    Some(field) => Err(no_field(type_name::<$value>(), field))
+                }
+            }
+
+            fn set_path(&mut self, path : &[&str], value : Value) -> Result<(), Error> {
+
+                match path.first() {
+                    None => { *self = serde_json::from_value(value)?; Ok(()) },
+                    Some(field) => Err(no_field(type_name::<$value>(), field))
+                }
+            }
+        }
+    )*
+}}
+
+/// Implements Reflect for a type from a list of its fields, without checking the list is complete.
+/// Only for types which can't be destructured, like nalgebra vectors and tuples - use reflect_struct
+/// for everything else.
+#[macro_export]
+macro_rules! reflect_fields { ($($reflected:ty { $($field:tt $(: $mode:ident)?),* } $([$pattern:pat])?);* $(;)?) => {
+    $(
+        impl $crate::ecs::reflect::Reflect for $reflected {
+
+            fn fields(&self) -> Vec<$crate::ecs::reflect::FieldInfo> {
+                // Destructures the struct with every listed field, so a field missing from the list
+                // fails to compile.
+                $(let $pattern = self;)?
+                vec![$($crate::ecs::reflect::FieldInfo {
+                    name : stringify!($field),
+                    type_name : $crate::ecs::reflect::__private::type_name_of(&self.$field),
+                    read_only : $crate::__reflect_read_only!($($mode)?)
+                }),*]
+            }
+
+            fn get_path(&self, path : &[&str]) -> Result<$crate::ecs::reflect::__private::Value, $crate::ecs::reflect::__private::Error> {
+
+                use $crate::ecs::reflect::__private::*;
+
+                match path.split_first() {
+                    None => {
+                        let mut object = Map::new();
+                        $(object.insert(String::from(stringify!($field)), Reflect::get_path(&self.$field, &[])?);)*
+                        Ok(Value::Object(object))
+                    },
+                    $(Some((&stringify!($field), rest)) => Reflect::get_path(&self.$field, rest),)*
+                    Some((field, _)) => Err(no_field(type_name::<$reflected>(), field))
+                }
+            }
+
+            fn set_path(&mut self, path : &[&str], value : $crate::ecs::reflect::__private::Value) -> Result<(), $crate::ecs::reflect::__private::Error> {
+
+                use $crate::ecs::reflect::__private::*;
+
+                match path.split_first() {
+                    // Only the fields present in the object are set. Nothing is set if any of them
+                    // are read-only.
+                    None => {
+                        let mut object = match value {
+                            Value::Object(object) => object,
+                            _ => return Err(err_msg(format!("Expected an object for {}", type_name::<$reflected>())))
+                        };
+                        $(if $crate::__reflect_read_only!($($mode)?) && object.contains_key(stringify!($field)) {
+                            return Err(read_only_field(type_name::<$reflected>(), stringify!($field)))
+                        })*
+                        $(if let Some(value) = object.remove(stringify!($field)) {
+                            Reflect::set_path(&mut self.$field, &[], value)?;
+                        })*
+                        Ok(())
+                    },
+                    $(Some((&stringify!($field), _)) if $crate::__reflect_read_only!($($mode)?) =>
+                        Err(read_only_field(type_name::<$reflected>(), stringify!($field))),)*
+                    $(Some((&stringify!($field), rest)) => Reflect::set_path(&mut self.$field, rest, value),)*
+                    Some((field, _)) => Err(no_field(type_name::<$reflected>(), field))
+                }
+            }
+        }
+    )*
+}}
+
+/// Implements Reflect for a struct from a list of its fields, e.g:
+/// reflect_struct!(PositionComponent { position });
+/// Every field has to be listed - a missing one fails to compile. A field followed by : read_only
+/// can be read but not set.
+#[macro_export]
+macro_rules! reflect_struct { ($($reflected:ident { $($field:ident $(: $mode:ident)?),* });* $(;)?) => {
+    $crate::reflect_fields!($($reflected { $($field $(: $mode)?),* } [$reflected { $($field : _),* }]);*);
+}}
+
+/// Whether a field listed in reflect_fields is read-only. Anything other than read_only fails to
+/// compile.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __reflect_read_only {
+    () => { false };
+    (read_only) => { true };
+}
+
+reflect_value!(f32, f64, i32, u32, u64, usize, bool, String);
+
+reflect_fields!(
+    Vector2<f32> { x, y };
+    Vector3<f32> { x, y, z };
+    (f32, f32, f32, f32) { 0, 1, 2, 3 }
+);
+
+reflect_struct!(
+    Entity { index, generation };
+    Texture { path, uniform_name, texture_id : read_only, number, active_texture_enum };
+
+    PositionComponent { position };
+    VelocityComponent { velocity };
+    PreviousPositionComponent { position };
+    RotationComponent { rotation };
+    RotationUpdateComponent { axis, angle };
+    ScaleComponent { scale };
+    ParentComponent { parent : read_only };
+    ChildrenComponent { children : read_only };
+    GlobalTransformComponent { matrix };
+    ColorComponent { color };
+    RenderComponent { shader_program : read_only, vertex_array_object : read_only, shader, mesh };
+    TextureMixComponent { textures, opacity };
+    TextureUpdateComponent { opacity_change };
+    OrthographicCameraComponent { dimensions, view, projection };
+    BoxCollider2DComponent { size, position, corners };
+    NameComponent { name : read_only };
+    TagsComponent { tags }
+);
+
+/// Matrices are reflected as a flat list of 16 values in column-major order.
+impl Reflect for Matrix4<f32> {
+
+    fn fields(&self) -> Vec<FieldInfo> {
+        Vec::new()
+    }
+
+    fn get_path(&self, path : &[&str]) -> Result<Value, Error> {
+
+        match path.first() {
+            None => Ok(serde_json::to_value(self.as_slice())?),
+            Some(field) => Err(no_field(type_name::<Matrix4<f32>>(), field))
+        }
+    }
+
+    fn set_path(&mut self, path : &[&str], value : Value) -> Result<(), Error> {
+
+        if let Some(field) = path.first() {
+            return Err(no_field(type_name::<Matrix4<f32>>(), field))
+        }
+
+        let values : Vec<f32> = serde_json::from_value(value)?;
+
+        if values.len() != 16 {
+            return Err(err_msg("Expected 16 values for a matrix."))
+        }
+
+        self.as_mut_slice().copy_from_slice(&values);
+
+        Ok(())
+    }
+}
+
+/// Elements are reached by index. A whole vector can only be set from a list of the same length.
+impl<T : Reflect> Reflect for Vec<T> {
+
+    fn fields(&self) -> Vec<FieldInfo> {
+        Vec::new()
+    }
+
+    fn get_path(&self, path : &[&str]) -> Result<Value, Error> {
+
+        match path.split_first() {
+            None => Ok(Value::Array(self.iter().map(|element| element.get_path(&[])).collect::<Result<Vec<Value>, Error>>()?)),
+            Some((index, rest)) => element(self, index)?.get_path(rest)
+        }
+    }
+
+    fn set_path(&mut self, path : &[&str], value : Value) -> Result<(), Error> {
+
+        match path.split_first() {
+            None => {
+                let values = match value {
+                    Value::Array(values) if values.len() == self.len() => values,
+                    _ => return Err(err_msg(format!("Expected a list of {} values.", self.len())))
+                };
+
+                for (element, value) in self.iter_mut().zip(values) {
+                    element.set_path(&[], value)?;
+                }
+
+                Ok(())
+            },
+            Some((index, rest)) => {
+                let index = parse_index(self.len(), index)?;
+                self[index].set_path(rest, value)
+            }
+        }
+    }
+}
+
+fn element<'a, T>(elements : &'a [T], index : &str) -> Result<&'a T, Error> {
+
+    Ok(&elements[parse_index(elements.len(), index)?])
+}
+
+fn parse_index(len : usize, index : &str) -> Result<usize, Error> {
+
+    match index.parse::<usize>() {
+        Ok(index) if index < len => Ok(index),
+        _ => Err(err_msg(format!("Index {} is out of range, there are {} elements.", index, len)))
+    }
+}
+
+/// What the exported macros expand to, so they work in crates without the same imports.
+#[doc(hidden)]
+pub mod __private {
+
+    pub use super::{Reflect, type_name_of, no_field, read_only_field};
+    pub use failure::{Error, err_msg};
+    pub use serde_json::{Map, Value};
+    pub use std::any::type_name;
+}
+
+pub fn type_name_of<T>(_ : &T) -> &'static str {
+    type_name::<T>()
+}
+
+pub fn no_field(type_name : &str, field : &str) -> Error {
+    err_msg(format!("{} has no field {}", type_name, field))
+}
+
+pub fn read_only_field(type_name : &str, field : &str) -> Error {
+    err_msg(format!("{}.{} is read-only", type_name, field))
+}
+
+/// Registers every engine component for reflection.
+pub fn register_engine_components(state : &mut GameState) {
+
+    state.register_reflect::<PositionComponent>();
+    state.register_reflect::<VelocityComponent>();
+    state.register_reflect::<PreviousPositionComponent>();
+    state.register_reflect::<RotationComponent>();
+    state.register_reflect::<RotationUpdateComponent>();
+    state.register_reflect::<ScaleComponent>();
+    state.register_reflect::<ParentComponent>();
+    state.register_reflect::<ChildrenComponent>();
+    state.register_reflect::<GlobalTransformComponent>();
+    state.register_reflect::<ColorComponent>();
+    state.register_reflect::<RenderComponent>();
+    state.register_reflect::<TextureMixComponent>();
+    state.register_reflect::<TextureUpdateComponent>();
+    state.register_reflect::<OrthographicCameraComponent>();
+    state.register_reflect::<BoxCollider2DComponent>();
+    state.register_reflect::<NameComponent>();
+    state.register_reflect::<TagsComponent>();
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn read_only_fields_can_be_read_but_not_set() {
+
+        let mut state = GameState::create_initial_state();
+
+        let parent = GameState::create_entity(&mut state).build();
+        let child = GameState::create_entity(&mut state).build();
+
+        state.set_parent(&child, &parent).unwrap();
+        state.set_name(&child, "child").unwrap();
+
+        assert_eq!(state.reflect_get(&child, "NameComponent.name").unwrap(), json!("child"));
+
+        assert!(state.reflect_set(&child, "NameComponent.name", json!("renamed")).is_err());
+        assert!(state.reflect_set(&child, "ParentComponent.parent.index", json!(7)).is_err());
+        assert!(state.reflect_set(&parent, "ChildrenComponent.children", json!([])).is_err());
+
+        assert_eq!(state.find_by_name("child"), Some(child));
+        assert_eq!(state.get::<ParentComponent>(&child).unwrap().parent, parent);
+        assert_eq!(state.get::<ChildrenComponent>(&parent).unwrap().children, vec![child]);
+
+        let fields = state.reflect_fields(&child, "ParentComponent").unwrap();
+
+        assert!(fields[0].read_only);
+    }
+
+    #[test]
+    fn objects_with_a_read_only_field_set_nothing() {
+
+        let mut render = RenderComponent { shader_program : 3, vertex_array_object : 4, shader : String::from("basic"), mesh : String::from("quad") };
+
+        assert!(render.set_path(&[], json!({ "mesh" : "triangle", "shader_program" : 0 })).is_err());
+
+        assert_eq!(render.mesh, "quad");
+        assert_eq!(render.shader_program, 3);
+
+        render.set_path(&[], json!({ "mesh" : "triangle" })).unwrap();
+
+        assert_eq!(render.mesh, "triangle");
+    }
+}
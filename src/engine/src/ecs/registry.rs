@@ -0,0 +1,347 @@
+//! COMPONENT REGISTRY
+//! Keeps a record of every component type which has storage in the world. Component maps live in
+//! an AnyMap, which can't be iterated, so anything that needs to touch every map (despawning,
+//! debugging, snapshots) goes through the type-erased functions stored here. Reflected types are
+//! also kept here, looked up by their short name, and scene components by their scene name.
+
+use std::any::{TypeId, type_name};
+use std::collections::{BTreeMap, HashMap};
+use anymap::AnyMap;
+use crate::generational_index::generational_index::{GenerationalIndex, GenerationalIndexArray};
+use crate::ecs::Component;
+use crate::ecs::snapshot::Snapshot;
+use crate::ecs::reflect::{Reflect, FieldInfo};
+use crate::game_state::GameState;
+use failure::{Error, err_msg};
+use serde_json::Value;
+use crate::ecs::storage::{Column, TypedColumn};
+use crate::scene::SceneComponent;
+
+/// Type-erased details of a single component type.
+#[derive(Clone, Copy)]
+pub struct ComponentInfo {
+
+    pub type_id : TypeId,
+    pub name : &'static str,
+    pub contains : fn(&AnyMap, &GenerationalIndex) -> bool,
+    pub len : fn(&AnyMap) -> usize,
+    pub remove : fn(&mut AnyMap, &GenerationalIndex, u64),
+    pub prune_removed : fn(&mut AnyMap, u64)
+}
+
+impl ComponentInfo {
+
+    pub fn of<T : 'static>() -> ComponentInfo {
+
+        ComponentInfo {
+            type_id : TypeId::of::<T>(),
+            name : type_name::<T>(),
+            contains : contains_in_map::<T>,
+            len : len_of_map::<T>,
+            remove : remove_from_map::<T>,
+            prune_removed : prune_removed_in_map::<T>
+        }
+    }
+}
+
+fn contains_in_map<T : 'static>(components : &AnyMap, index : &GenerationalIndex) -> bool {
+
+    match components.get::<GenerationalIndexArray<T>>() {
+        Some(map) => map.contains(index),
+        None => false
+    }
+}
+
+fn len_of_map<T : 'static>(components : &AnyMap) -> usize {
+
+    components.get::<GenerationalIndexArray<T>>().map_or(0, |map| map.len())
+}
+
+fn remove_from_map<T : 'static>(components : &mut AnyMap, index : &GenerationalIndex, tick : u64) {
+
+    if let Some(map) = components.get_mut::<GenerationalIndexArray<T>>() {
+        map.remove(index, tick);
+    }
+}
+
+fn prune_removed_in_map<T : 'static>(components : &mut AnyMap, before : u64) {
+
+    if let Some(map) = components.get_mut::<GenerationalIndexArray<T>>() {
+        map.prune_removed(before);
+    }
+}
+
+/// Type-erased functions for copying a component type which supports snapshots, either into a
+/// snapshot (take) or back out of one (restore).
+/// SEE: snapshot
+#[derive(Clone, Copy)]
+pub struct SnapshotInfo {
+
+    pub take_map : fn(&AnyMap, &mut AnyMap, u64) -> Result<(), Error>,
+    pub restore_map : fn(&AnyMap, &mut AnyMap, u64) -> Result<(), Error>,
+    pub take_column : CopyColumn,
+    pub restore_column : CopyColumn
+}
+
+/// Copies a table column at the given tick.
+pub type CopyColumn = fn(&dyn Column, u64) -> Result<Box<dyn Column>, Error>;
+
+impl SnapshotInfo {
+
+    pub fn of<T : Snapshot>() -> SnapshotInfo {
+
+        SnapshotInfo {
+            take_map : |from, to, tick| copy_map::<T>(from, to, tick, take_value::<T>),
+            restore_map : |from, to, tick| copy_map::<T>(from, to, tick, T::restore),
+            take_column : |column, tick| copy_column::<T>(column, tick, take_value::<T>),
+            restore_column : |column, tick| copy_column::<T>(column, tick, T::restore)
+        }
+    }
+}
+
+fn take_value<T : Snapshot>(value : &T) -> Result<T, Error> {
+
+    Ok(value.snapshot())
+}
+
+fn copy_map<T : Snapshot>(from : &AnyMap, to : &mut AnyMap, tick : u64, copy : fn(&T) -> Result<T, Error>) -> Result<(), Error> {
+
+    if let Some(map) = from.get::<GenerationalIndexArray<T>>() {
+        to.insert(map.copy_with(copy, tick)?);
+    }
+
+    Ok(())
+}
+
+fn copy_column<T : Snapshot>(column : &dyn Column, tick : u64, copy : fn(&T) -> Result<T, Error>) -> Result<Box<dyn Column>, Error> {
+
+    let column = column.as_any().downcast_ref::<TypedColumn<T>>().unwrap();
+
+    Ok(Box::new(column.copy_with(copy, tick)?))
+}
+
+/// Runs when a component is added to or removed from an entity.
+/// SEE: GameState::on_add
+pub type ComponentHook = fn(&mut GameState, &GenerationalIndex);
+
+#[derive(Clone, Default)]
+pub struct ComponentHooks {
+
+    pub on_add : Vec<ComponentHook>,
+    pub on_remove : Vec<ComponentHook>
+}
+
+/// Type-erased functions for reading and writing the fields of a reflected component type.
+/// SEE: reflect
+#[derive(Clone, Copy)]
+pub struct ReflectInfo {
+
+    pub name : &'static str,
+    pub type_id : TypeId,
+    pub has : fn(&GameState, &GenerationalIndex) -> bool,
+    pub fields : fn(&GameState, &GenerationalIndex) -> Option<Vec<FieldInfo>>,
+    pub get : fn(&GameState, &GenerationalIndex, &[&str]) -> Result<Value, Error>,
+    pub set : fn(&mut GameState, &GenerationalIndex, &[&str], Value) -> Result<(), Error>
+}
+
+impl ReflectInfo {
+
+    pub fn of<T : Component + Reflect>() -> ReflectInfo {
+
+        ReflectInfo {
+            name : short_name(type_name::<T>()),
+            type_id : TypeId::of::<T>(),
+            has : |state, index| state.has::<T>(index),
+            fields : |state, index| state.get::<T>(index).map(|component| component.fields()),
+            get : get_reflected::<T>,
+            set : set_reflected::<T>
+        }
+    }
+}
+
+fn get_reflected<T : Component + Reflect>(state : &GameState, index : &GenerationalIndex, path : &[&str]) -> Result<Value, Error> {
+
+    match state.get::<T>(index) {
+        Some(component) => component.get_path(path),
+        None => Err(missing::<T>(index))
+    }
+}
+
+fn set_reflected<T : Component + Reflect>(state : &mut GameState, index : &GenerationalIndex, path : &[&str], value : Value) -> Result<(), Error> {
+
+    match state.get_mut::<T>(index) {
+        Some(component) => component.set_path(path, value),
+        None => Err(missing::<T>(index))
+    }
+}
+
+/// Type-erased functions for saving a game's component type in scenes and loading it back.
+/// SEE: scene::SceneComponent
+#[derive(Clone, Copy)]
+pub struct SceneComponentInfo {
+
+    pub capture : fn(&GameState, &GenerationalIndex) -> Option<Value>,
+    pub spawn : fn(&mut GameState, &GenerationalIndex, Value) -> Result<(), Error>
+}
+
+impl SceneComponentInfo {
+
+    pub fn of<T : SceneComponent>() -> SceneComponentInfo {
+
+        SceneComponentInfo {
+            capture : |state, index| state.get::<T>(index).map(T::to_scene),
+            spawn : |state, index, value| { state.add_component_to(T::from_scene(value)?, index); Ok(()) }
+        }
+    }
+}
+
+fn missing<T>(index : &GenerationalIndex) -> Error {
+
+    err_msg(format!("Entity {:?} has no {}", index, short_name(type_name::<T>())))
+}
+
+/// The type's name without its module path, e.g. PositionComponent.
+fn short_name(name : &'static str) -> &'static str {
+
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+pub struct ComponentRegistry {
+
+    components : HashMap<TypeId, ComponentInfo>,
+    snapshots : HashMap<TypeId, SnapshotInfo>,
+    reflected : HashMap<&'static str, ReflectInfo>,
+    scene_components : BTreeMap<&'static str, SceneComponentInfo>,
+    hooks : HashMap<TypeId, ComponentHooks>
+}
+
+impl Default for ComponentRegistry {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ComponentRegistry {
+
+    pub fn new() -> ComponentRegistry {
+
+        ComponentRegistry { components : HashMap::new(), snapshots : HashMap::new(), reflected : HashMap::new(),
+                            scene_components : BTreeMap::new(), hooks : HashMap::new() }
+    }
+
+    /// Registers T. Registering the same type twice has no effect.
+    pub fn register<T : 'static>(&mut self) {
+
+        self.components.entry(TypeId::of::<T>()).or_insert_with(ComponentInfo::of::<T>);
+    }
+
+    pub fn contains<T : 'static>(&self) -> bool {
+
+        self.components.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get(&self, type_id : &TypeId) -> Option<&ComponentInfo> {
+
+        self.components.get(type_id)
+    }
+
+    /// Records that T supports snapshots.
+    pub fn register_snapshot<T : Snapshot>(&mut self) {
+
+        self.snapshots.insert(TypeId::of::<T>(), SnapshotInfo::of::<T>());
+    }
+
+    pub fn snapshot_info(&self, type_id : &TypeId) -> Option<&SnapshotInfo> {
+
+        self.snapshots.get(type_id)
+    }
+
+    pub fn add_on_add<T : 'static>(&mut self, hook : ComponentHook) {
+
+        self.hooks.entry(TypeId::of::<T>()).or_default().on_add.push(hook);
+    }
+
+    pub fn add_on_remove<T : 'static>(&mut self, hook : ComponentHook) {
+
+        self.hooks.entry(TypeId::of::<T>()).or_default().on_remove.push(hook);
+    }
+
+    /// The hooks for a component type. Copied out, so they can be run against the world.
+    pub fn hooks(&self, type_id : &TypeId) -> ComponentHooks {
+
+        self.hooks.get(type_id).cloned().unwrap_or_default()
+    }
+
+    /// Every component type with at least one on_remove hook.
+    pub fn types_with_on_remove(&self) -> Vec<TypeId> {
+
+        self.hooks.iter()
+            .filter(|(_, hooks)| !hooks.on_remove.is_empty())
+            .map(|(type_id, _)| *type_id)
+            .collect()
+    }
+
+    /// Makes T reachable through reflection by its short name.
+    pub fn register_reflect<T : Component + Reflect>(&mut self) {
+
+        let info = ReflectInfo::of::<T>();
+
+        self.reflected.insert(info.name, info);
+    }
+
+    pub fn reflect_info(&self, name : &str) -> Option<&ReflectInfo> {
+
+        self.reflected.get(name)
+    }
+
+    /// The short names of every reflected type, sorted alphabetically.
+    pub fn reflected_names(&self) -> Vec<&'static str> {
+
+        let mut names = self.reflected.keys().cloned().collect::<Vec<&'static str>>();
+
+        names.sort();
+
+        names
+    }
+
+    /// Lets T be saved in scenes under the given name.
+    pub fn register_scene_component<T : SceneComponent>(&mut self, name : &'static str) {
+
+        self.scene_components.insert(name, SceneComponentInfo::of::<T>());
+    }
+
+    pub fn scene_component(&self, name : &str) -> Option<&SceneComponentInfo> {
+
+        self.scene_components.get(name)
+    }
+
+    /// Every scene component, sorted by name.
+    pub fn scene_components(&self) -> impl Iterator<Item = (&&'static str, &SceneComponentInfo)> {
+
+        self.scene_components.iter()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ComponentInfo> {
+
+        self.components.values()
+    }
+
+    /// The names of every registered component type, sorted alphabetically.
+    pub fn names(&self) -> Vec<&'static str> {
+
+        let mut names = self.components.values().map(|info| info.name).collect::<Vec<&'static str>>();
+
+        names.sort();
+
+        names
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
@@ -1,6 +1,8 @@
 use crate::ecs::system::System;
-use crate::generational_index::generational_index::{GenerationalIndexArray, GenerationalIndex};
+use crate::game_state::GameState;
 use crate::ecs::*;
+use crate::ecs::transform_propagation_system::interpolated_position;
+use crate::time::Time;
 use failure::Error;
 use std::ffi::CString;
 
@@ -8,91 +10,77 @@ pub struct RenderSystem;
 
 impl<'a> System<'a> for RenderSystem {
 
-    type SystemInput = (&'a GenerationalIndexArray<RenderComponent>,
-                        &'a GenerationalIndexArray<PositionComponent>,
-                        &'a GenerationalIndexArray<ColorComponent>,
-                        &'a GenerationalIndexArray<TextureMixComponent>,
-                        &'a GenerationalIndexArray<ScaleComponent>,
-                        &'a OrthographicCameraComponent,
-                        &'a GenerationalIndexArray<RotationComponent>);
+    type SystemInput = &'a mut GameState;
 
     fn run(input: Self::SystemInput) -> Result<(), Error> {
 
-        let shaders = &input.0.entries;
-
-        let mut idx = 0;
-
-        shaders.into_iter().try_for_each(|shader| -> Result<(), Error> {
-
-            if let Some(shader_program) = shader {
-
-                let index = shader_program.owned_entity;
-
-                unsafe {
-
-                    gl::BindVertexArray(shader_program.value.vertex_array_object);
-
-                    // Set shader program being used.
-                    gl::UseProgram(shader_program.value.shader_program);
+        let (view, projection) = {
+            let camera = input.resource::<OrthographicCameraComponent>();
+            (camera.view, camera.projection)
+        };
 
-                    // START POSITION RENDERING VARIABLES ------------------------------------------
+        let alpha = input.try_resource::<Time>().map_or(1.0, |time| time.alpha());
 
-                    //println!("Position and scale");
-                    let position = input.1.get(&index).unwrap();
+        // Fetched in one query rather than a lookup per component, so table components are read
+        // in order.
+        let renderables = input.query::<(&RenderComponent, Option<&GlobalTransformComponent>, Option<&PositionComponent>,
+                                         Option<&RotationComponent>, Option<&ScaleComponent>, Option<&ColorComponent>,
+                                         Option<&TextureMixComponent>, Option<&PreviousPositionComponent>)>();
 
-                    let mut scale_vec : Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+        let mut idx = 0;
 
-                    if let Some(scale) = input.4.get(&index) {
-                        scale_vec = scale.scale;
-                        //println!("Some")
-                    } else {
-                        //println!("None")
-                    }
+        renderables.into_iter().try_for_each(|(shader_program, global, position, rotation, scale, color, texture_mix, previous)| -> Result<(), Error> {
 
-                    let rotation_comp = input.6.get(&index).unwrap();
+            unsafe {
 
-                    let rotation = nalgebra::Matrix4::from_scaled_axis(rotation_comp.rotation);
+                gl::BindVertexArray(shader_program.vertex_array_object);
 
-                    let translation = nalgebra::Matrix4::new_translation(&position.position) * rotation;
+                // Set shader program being used.
+                gl::UseProgram(shader_program.shader_program);
 
-                    let model = translation * nalgebra::Matrix4::new_nonuniform_scaling(&scale_vec);
+                // START POSITION RENDERING VARIABLES ------------------------------------------
 
-                    RenderSystem::set_mat4(shader_program.value.shader_program, "Model", model)?;
+                // Uses the global transform worked out by the transform propagation system, which
+                // places children relative to their parents and has already been interpolated.
+                // Moving entities are drawn part way between their last two fixed steps.
+                let model = match global {
+                    Some(global) => global.matrix,
+                    None => RenderSystem::get_model(interpolated_position(position, previous, alpha), rotation, scale)
+                };
 
-                    RenderSystem::set_mat4(shader_program.value.shader_program, "View", input.5.view)?;
+                RenderSystem::set_mat4(shader_program.shader_program, "Model", model)?;
 
-                    RenderSystem::set_mat4(shader_program.value.shader_program, "Projection", input.5.projection)?;
+                RenderSystem::set_mat4(shader_program.shader_program, "View", view)?;
 
-                    // END OF POSITION RENDERING VARIABLES -----------------------------------------
+                RenderSystem::set_mat4(shader_program.shader_program, "Projection", projection)?;
 
-                    // COLOR RENDERING VARIABLES
-                    if let Some(color) = input.2.get(&index).take() {
+                // END OF POSITION RENDERING VARIABLES -----------------------------------------
 
-                        RenderSystem::set_vector4(shader_program.value.shader_program, "Color", (color.color.0, color.color.1, color.color.2, color.color.3))?;
+                // COLOR RENDERING VARIABLES
+                if let Some(color) = color {
 
-                    }
-                    // TEXTURE RENDERING VARIABLES
-                    let texture_mix = input.3.get(&index);
+                    RenderSystem::set_vector4(shader_program.shader_program, "Color", (color.color.0, color.color.1, color.color.2, color.color.3))?;
 
-                    if let Some(texture_comp) = texture_mix {
+                }
+                // TEXTURE RENDERING VARIABLES
+                if let Some(texture_comp) = texture_mix {
 
-                        RenderSystem::set_bool(shader_program.value.shader_program, true, "usingTextures")?;
+                    RenderSystem::set_bool(shader_program.shader_program, true, "usingTextures")?;
 
-                        for texture in texture_comp.textures.iter() {
+                    for texture in texture_comp.textures.iter() {
 
-                            RenderSystem::set_int(shader_program.value.shader_program, &texture.uniform_name, texture.number)?;
-                            RenderSystem::set_float(shader_program.value.shader_program, "opacity", texture_comp.opacity)?;
-                            gl::ActiveTexture(texture.active_texture_enum);
-                            gl::BindTexture(gl::TEXTURE_2D, texture.texture_id);
-                        }
+                        RenderSystem::set_int(shader_program.shader_program, &texture.uniform_name, texture.number)?;
+                        RenderSystem::set_float(shader_program.shader_program, "opacity", texture_comp.opacity)?;
+                        gl::ActiveTexture(texture.active_texture_enum);
+                        gl::BindTexture(gl::TEXTURE_2D, texture.texture_id);
                     }
+                }
 
-                    // DRAW VERTICES
-                    gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
-                    RenderSystem::set_bool(shader_program.value.shader_program, false, "usingTextures")?;
+                // DRAW VERTICES
+                gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+                RenderSystem::set_bool(shader_program.shader_program, false, "usingTextures")?;
 
-                } idx += 1;
-            }
+            } idx += 1;
             Ok(())
         })?;
         Ok(())
@@ -101,10 +89,33 @@ impl<'a> System<'a> for RenderSystem {
 
 impl RenderSystem {
 
+    /// Builds the model matrix straight from the entity's position, rotation and scale.
+    pub fn get_model(position : Vector3<f32>, rotation : Option<&RotationComponent>,
+                     scale : Option<&ScaleComponent>) -> Matrix4<f32> {
+
+        let mut scale_vec : Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+
+        if let Some(scale) = scale {
+            scale_vec = scale.scale;
+        }
+
+        let rotation = rotation.map_or(Vector3::new(0.0, 0.0, 0.0), |rotation| rotation.rotation);
+
+        let rotation = nalgebra::Matrix4::from_scaled_axis(rotation);
+
+        let translation = nalgebra::Matrix4::new_translation(&position) * rotation;
+
+        translation * nalgebra::Matrix4::new_nonuniform_scaling(&scale_vec)
+    }
+
+    /// Sets the named bool uniform on the program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_bool(id : gl::types::GLuint, value : bool, name : &str) -> Result<(), Error> {
         let condition : u32 = match value {
-            true => (1),
-            false => (0)
+            true => 1,
+            false => 0
         };
 
         gl::Uniform1ui(gl::GetUniformLocation(id, CString::new(name)?.as_ptr()), condition);
@@ -112,6 +123,10 @@ impl RenderSystem {
         Ok(())
     }
 
+    /// Sets the named vec4 uniform on the program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_vector4(id : gl::types::GLuint, name : &str, vec : (f32, f32, f32, f32)) -> Result<(), Error>{
 
         gl::Uniform4f(gl::GetUniformLocation(id, CString::new(name)?.as_ptr()), vec.0, vec.1, vec.2, vec.3);
@@ -119,6 +134,10 @@ impl RenderSystem {
         Ok(())
     }
 
+    /// Sets the named vec2 uniform on the program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_vector2(id : gl::types::GLuint, name : &str, vec : (f32, f32)) -> Result<(), Error> {
 
         gl::Uniform2f(gl::GetUniformLocation(id, CString::new(name)?.as_ptr()), vec.0, vec.1);
@@ -126,6 +145,10 @@ impl RenderSystem {
         Ok(())
     }
 
+    /// Sets the named int uniform on the program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_int(id : gl::types::GLuint, name : &str, number : i32) -> Result<(), Error> {
 
         gl::Uniform1i(gl::GetUniformLocation(id, CString::new(name)?.as_ptr()), number);
@@ -133,6 +156,10 @@ impl RenderSystem {
         Ok(())
     }
 
+    /// Sets the named float uniform on the program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_float(id : gl::types::GLuint, name : &str, number : f32) -> Result<(), Error> {
 
         gl::Uniform1f(gl::GetUniformLocation(id, CString::new(name)?.as_ptr()), number);
@@ -140,6 +167,10 @@ impl RenderSystem {
         Ok(())
     }
 
+    /// Sets the named mat4 uniform on the program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_mat4(id : gl::types::GLuint, name : &str, mat : nalgebra::Matrix4<f32> ) -> Result<(), Error> {
 
         gl::UniformMatrix4fv(gl::GetUniformLocation(id, CString::new(name)?.as_ptr()),1, gl::FALSE, mat.as_ptr());
@@ -0,0 +1,321 @@
+//! SCHEDULE
+//! Holds every system the engine runs, grouped into stages. Stages always run in the order below,
+//! except FixedUpdate, which runs once for every fixed step the Time resource has banked (see time);
+//! within a stage, systems run in the order they were added unless a before/after constraint says
+//! otherwise. Startup systems run once, before the first frame. Systems run one at a time, on the
+//! thread which runs the schedule.
+//!
+//! The game state's change tick moves forward before every system, and each system remembers the
+//! tick of its last run, which is what Added/Changed/removed compare against.
+//!
+//! Commands recorded by a system are applied as soon as it finishes, before the next system starts.
+
+use failure::{Error, err_msg};
+use std::time::Instant;
+use crate::game_state::GameState;
+use crate::ecs::change_detection;
+use crate::time::Time;
+use crate::profiler::Profiler;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Stage {
+
+    Startup,
+    Input,
+    FixedUpdate,
+    Update,
+    PostUpdate,
+    Render
+}
+
+impl Stage {
+
+    /// Every stage which runs once per frame, in execution order.
+    pub fn frame_stages() -> [Stage; 5] {
+        [Stage::Input, Stage::FixedUpdate, Stage::Update, Stage::PostUpdate, Stage::Render]
+    }
+}
+
+pub type SystemFn = Box<dyn FnMut(&mut GameState) -> Result<(), Error>>;
+pub type RunCondition = Box<dyn Fn(&GameState) -> bool>;
+
+/// A system plus everything the schedule needs to know about it. Built with a chain of calls, e.g:
+/// SystemDescriptor::new("follow_mouse", |state| FollowMouseSystem::run(state)).after("check_collision")
+pub struct SystemDescriptor {
+
+    pub name : String,
+    system : SystemFn,
+    before : Vec<String>,
+    after : Vec<String>,
+    conditions : Vec<RunCondition>,
+    last_run : u64
+}
+
+impl SystemDescriptor {
+
+    pub fn new<F>(name : &str, system : F) -> SystemDescriptor
+        where F : FnMut(&mut GameState) -> Result<(), Error> + 'static {
+
+        SystemDescriptor {
+            name : String::from(name),
+            system : Box::new(system),
+            before : Vec::new(),
+            after : Vec::new(),
+            conditions : Vec::new(),
+            last_run : 0
+        }
+    }
+
+    /// This system must run before the named system (when both are in the same stage).
+    pub fn before(mut self, name : &str) -> Self {
+
+        self.before.push(String::from(name));
+        self
+    }
+
+    /// This system must run after the named system (when both are in the same stage).
+    pub fn after(mut self, name : &str) -> Self {
+
+        self.after.push(String::from(name));
+        self
+    }
+
+    /// The system is skipped on any frame where the condition returns false. Multiple conditions
+    /// must all pass.
+    pub fn run_if<F>(mut self, condition : F) -> Self where F : Fn(&GameState) -> bool + 'static {
+
+        self.conditions.push(Box::new(condition));
+        self
+    }
+
+    pub fn should_run(&self, state : &GameState) -> bool {
+
+        self.conditions.iter().all(|condition| condition(state))
+    }
+
+    pub fn run(&mut self, state : &mut GameState) -> Result<(), Error> {
+
+        let tick = state.change_tick;
+
+        change_detection::set_last_run_tick(self.last_run);
+
+        let result = (self.system)(state);
+
+        self.last_run = tick;
+
+        result
+    }
+}
+
+struct StageSystems {
+
+    stage : Stage,
+    systems : Vec<SystemDescriptor>,
+    sorted : bool
+}
+
+pub struct Schedule {
+
+    stages : Vec<StageSystems>,
+    started : bool,
+    last_frame_tick : u64
+}
+
+impl Default for Schedule {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Schedule {
+
+    pub fn new() -> Schedule {
+
+        let stages = [Stage::Startup, Stage::Input, Stage::FixedUpdate, Stage::Update, Stage::PostUpdate, Stage::Render]
+            .iter()
+            .map(|stage| StageSystems { stage : *stage, systems : Vec::new(), sorted : true })
+            .collect();
+
+        Schedule { stages, started : false, last_frame_tick : 0 }
+    }
+
+    /// True if the Time resource has a whole fixed step banked, which it uses up. Without a Time
+    /// resource, FixedUpdate never runs.
+    fn fixed_step_due(state : &mut GameState) -> bool {
+
+        match state.try_resource_mut::<Time>() {
+            Some(time) => time.expend_fixed_step(),
+            None => false
+        }
+    }
+
+    /// Adds a system to the given stage.
+    pub fn add_system(&mut self, stage : Stage, system : SystemDescriptor) -> &mut Self {
+
+        let stage = self.stage_mut(stage);
+
+        stage.systems.push(system);
+        stage.sorted = false;
+
+        self
+    }
+
+    /// Adds a system which runs once, before the first frame.
+    pub fn add_startup_system(&mut self, system : SystemDescriptor) -> &mut Self {
+
+        self.add_system(Stage::Startup, system)
+    }
+
+    /// Names of the systems in a stage, in the order they will run.
+    pub fn system_names(&mut self, stage : Stage) -> Result<Vec<String>, Error> {
+
+        let stage = self.stage_mut(stage);
+
+        Schedule::sort_stage(stage)?;
+
+        Ok(stage.systems.iter().map(|system| system.name.clone()).collect())
+    }
+
+    /// Runs the startup stage. Only does anything the first time it is called.
+    pub fn run_startup(&mut self, state : &mut GameState) -> Result<(), Error> {
+
+        if self.started {
+            return Ok(())
+        }
+
+        self.started = true;
+
+        self.run_stage(Stage::Startup, state)
+    }
+
+    /// Runs one frame: startup (the first time only), then every frame stage in order. Event
+    /// channels are swapped before the first stage.
+    pub fn run(&mut self, state : &mut GameState) -> Result<(), Error> {
+
+        self.run_startup(state)?;
+
+        state.update_events();
+
+        let frame_tick = state.change_tick;
+
+        for stage in Stage::frame_stages().iter() {
+
+            match stage {
+                Stage::FixedUpdate => while Schedule::fixed_step_due(state) {
+                    self.run_stage(*stage, state)?;
+                },
+                _ => self.run_stage(*stage, state)?
+            }
+        }
+
+        // Removals from the previous frame are kept so systems which ran before them still see them.
+        state.prune_removed(self.last_frame_tick);
+        self.last_frame_tick = frame_tick;
+
+        Ok(())
+    }
+
+    /// Runs every system in a single stage whose run conditions pass, applying the commands each
+    /// one records before the next one runs.
+    pub fn run_stage(&mut self, stage : Stage, state : &mut GameState) -> Result<(), Error> {
+
+        let stage_start = Instant::now();
+
+        let stage = self.stages.iter_mut().find(|systems| systems.stage == stage).unwrap();
+
+        Schedule::sort_stage(stage)?;
+
+        for system in stage.systems.iter_mut() {
+
+            if !system.should_run(state) {
+                continue
+            }
+
+            state.increment_change_tick();
+
+            let start = Instant::now();
+            let result = system.run(state);
+
+            if let Some(profiler) = state.try_resource_mut::<Profiler>() {
+                profiler.record(&system.name, "system", start, Instant::now());
+            }
+
+            result?;
+
+            state.apply_commands()?;
+        }
+
+        if let Some(profiler) = state.try_resource_mut::<Profiler>() {
+            profiler.record(&format!("{:?}", stage.stage), "stage", stage_start, Instant::now());
+        }
+
+        Ok(())
+    }
+
+    fn stage_mut(&mut self, stage : Stage) -> &mut StageSystems {
+
+        self.stages.iter_mut().find(|systems| systems.stage == stage).unwrap()
+    }
+
+    /// Orders a stage's systems so that every before/after constraint holds, keeping insertion
+    /// order wherever the constraints allow it. Fails if the constraints form a cycle.
+    fn sort_stage(stage : &mut StageSystems) -> Result<(), Error> {
+
+        if stage.sorted {
+            return Ok(())
+        }
+
+        let count = stage.systems.len();
+
+        let position = |name : &String| stage.systems.iter().position(|system| &system.name == name);
+
+        // edges[a] holds every system which has to wait for system a.
+        let mut edges : Vec<Vec<usize>> = vec![Vec::new(); count];
+        let mut waiting_on = vec![0; count];
+
+        for (index, system) in stage.systems.iter().enumerate() {
+
+            for name in system.before.iter() {
+                if let Some(other) = position(name) {
+                    edges[index].push(other);
+                    waiting_on[other] += 1;
+                }
+            }
+
+            for name in system.after.iter() {
+                if let Some(other) = position(name) {
+                    edges[other].push(index);
+                    waiting_on[index] += 1;
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(count);
+        let mut placed = vec![false; count];
+
+        while order.len() < count {
+
+            let next = (0..count).find(|index| !placed[*index] && waiting_on[*index] == 0);
+
+            let next = match next {
+                Some(next) => next,
+                None => return Err(err_msg(format!("Systems in the {:?} stage have circular ordering constraints.", stage.stage)))
+            };
+
+            placed[next] = true;
+            order.push(next);
+
+            for other in edges[next].iter() {
+                waiting_on[*other] -= 1;
+            }
+        }
+
+        let mut systems = stage.systems.drain(..).map(Some).collect::<Vec<Option<SystemDescriptor>>>();
+
+        stage.systems = order.iter().map(|index| systems[*index].take().unwrap()).collect();
+        stage.sorted = true;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+//! SNAPSHOTS
+//! A snapshot is an in-memory copy of the whole world - the allocator's generations and live
+//! entities, the name index and every component - which can be restored later, e.g. to retry a level or
+//! roll back to an earlier frame. The same snapshot can be restored any number of times.
+//!
+//! Component types opt in by implementing Snapshot and being registered with
+//! GameState::register_snapshot. Every engine component is registered when the state is created.
+//! Taking a snapshot fails if any entity has a component which hasn't opted in.
+//!
+//! Resources, events and queued commands are not part of the world, so they aren't captured.
+//! Restored components count as added on the tick they were restored on, so Added and Changed
+//! filters pick them up. Restoring despawns everything in the world first, so on_remove hooks run
+//! for the old world, but on_add hooks don't run for the restored one.
+//!
+//! Components which own GL objects don't keep them in snapshots. Their handles are left empty and
+//! rebuilt from their asset references when restored, so a snapshot never shares GL objects with
+//! the world.
+
+use crate::ecs::*;
+use crate::ecs::registry::ComponentRegistry;
+use crate::ecs::storage::Tables;
+use crate::game_state::{GameState, Entity};
+use crate::generational_index::generational_index::GenerationalIndexAllocator;
+use anymap::AnyMap;
+use failure::{Error, err_msg};
+use std::collections::HashMap;
+
+pub trait Snapshot : Component {
+
+    /// Copies the component into a snapshot.
+    fn snapshot(&self) -> Self;
+
+    /// Copies the component back out of a snapshot.
+    fn restore(&self) -> Result<Self, Error> {
+        Ok(self.snapshot())
+    }
+
+    /// Points any entity ids the copy holds at an entity's new id. Used by the undo history, which
+    /// brings despawned entities back with new ids.
+    fn remap(&mut self, _old : &Entity, _new : &Entity) {}
+}
+
+/// Implements Snapshot with Clone for each of the given types.
+#[macro_export]
+macro_rules! snapshot_by_clone { ($($component:ty),*) => {
+    $(
+        impl $crate::ecs::snapshot::Snapshot for $component {
+
+            fn snapshot(&self) -> Self {
+                self.clone()
+            }
+        }
+    )*
+}}
+
+snapshot_by_clone!(PositionComponent, VelocityComponent, PreviousPositionComponent, RotationComponent, RotationUpdateComponent, ScaleComponent,
+                   GlobalTransformComponent, ColorComponent,
+                   TextureUpdateComponent, OrthographicCameraComponent,
+                   BoxCollider2DComponent, NameComponent, TagsComponent);
+
+impl Snapshot for ParentComponent {
+
+    fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    fn remap(&mut self, old : &Entity, new : &Entity) {
+
+        if self.parent == *old {
+            self.parent = *new;
+        }
+    }
+}
+
+impl Snapshot for ChildrenComponent {
+
+    fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    fn remap(&mut self, old : &Entity, new : &Entity) {
+
+        self.children.iter_mut().filter(|child| *child == old).for_each(|child| *child = *new);
+    }
+}
+
+impl Snapshot for RenderComponent {
+
+    fn snapshot(&self) -> Self {
+        RenderComponent { shader_program : 0, vertex_array_object : 0, shader : self.shader.clone(), mesh : self.mesh.clone() }
+    }
+
+    fn restore(&self) -> Result<Self, Error> {
+        RenderComponent::from_assets(&self.shader, &self.mesh)
+    }
+}
+
+impl Snapshot for TextureMixComponent {
+
+    fn snapshot(&self) -> Self {
+
+        let textures = self.textures.iter()
+            .map(|texture| Texture {
+                path : texture.path.clone(),
+                uniform_name : texture.uniform_name.clone(),
+                texture_id : 0,
+                number : texture.number,
+                active_texture_enum : texture.active_texture_enum
+            })
+            .collect();
+
+        TextureMixComponent { textures, opacity : self.opacity }
+    }
+
+    fn restore(&self) -> Result<Self, Error> {
+        self.reload()
+    }
+}
+
+/// Registers every engine component for snapshots.
+pub fn register_engine_components(state : &mut GameState) {
+
+    state.register_snapshot::<PositionComponent>();
+    state.register_snapshot::<VelocityComponent>();
+    state.register_snapshot::<PreviousPositionComponent>();
+    state.register_snapshot::<RotationComponent>();
+    state.register_snapshot::<RotationUpdateComponent>();
+    state.register_snapshot::<ScaleComponent>();
+    state.register_snapshot::<ParentComponent>();
+    state.register_snapshot::<ChildrenComponent>();
+    state.register_snapshot::<GlobalTransformComponent>();
+    state.register_snapshot::<ColorComponent>();
+    state.register_snapshot::<RenderComponent>();
+    state.register_snapshot::<TextureMixComponent>();
+    state.register_snapshot::<TextureUpdateComponent>();
+    state.register_snapshot::<OrthographicCameraComponent>();
+    state.register_snapshot::<BoxCollider2DComponent>();
+    state.register_snapshot::<NameComponent>();
+    state.register_snapshot::<TagsComponent>();
+}
+
+pub struct WorldSnapshot {
+
+    components : AnyMap,
+    tables : Tables,
+    allocator : GenerationalIndexAllocator,
+    names : HashMap<String, Entity>
+}
+
+impl WorldSnapshot {
+
+    /// Copies the world.
+    pub fn take(state : &GameState) -> Result<WorldSnapshot, Error> {
+
+        let (components, tables) = copy_components(&state.registry, &state.components, &state.tables, state.change_tick, false)?;
+
+        Ok(WorldSnapshot {
+            components,
+            tables,
+            allocator : state.allocator.clone(),
+            names : state.names.clone()
+        })
+    }
+
+    /// Replaces the world with a copy of the snapshot. Any commands still queued were recorded
+    /// against the old world, so they are dropped.
+    pub fn restore(&self, state : &mut GameState) -> Result<(), Error> {
+
+        let (components, tables) = copy_components(&state.registry, &self.components, &self.tables, state.change_tick, true)?;
+
+        state.despawn_where(|_, _| true);
+
+        state.components = components;
+        state.tables = tables;
+        state.allocator = self.allocator.clone();
+        state.names = self.names.clone();
+
+        state.commands.take();
+
+        Ok(())
+    }
+
+    /// The number of live entities in the snapshot.
+    pub fn entity_count(&self) -> usize {
+
+        self.allocator.len()
+    }
+}
+
+/// Copies every sparse map and table, either into or out of a snapshot. Empty storage is copied even
+/// if its type hasn't opted in.
+fn copy_components(registry : &ComponentRegistry, components : &AnyMap, tables : &Tables, tick : u64, restoring : bool) -> Result<(AnyMap, Tables), Error> {
+
+    let mut copied = AnyMap::new();
+
+    for info in registry.iter() {
+
+        match registry.snapshot_info(&info.type_id) {
+            Some(snapshot) if restoring => (snapshot.restore_map)(components, &mut copied, tick)?,
+            Some(snapshot) => (snapshot.take_map)(components, &mut copied, tick)?,
+            None if (info.len)(components) == 0 => (),
+            None => return Err(unsupported(info.name))
+        }
+    }
+
+    let tables = tables.copy_with(|type_id, column| {
+
+        match registry.snapshot_info(type_id) {
+            Some(snapshot) if restoring => (snapshot.restore_column)(column, tick),
+            Some(snapshot) => (snapshot.take_column)(column, tick),
+            None if column.is_empty() => Ok(column.empty()),
+            None => Err(unsupported(tables.name_of(type_id).unwrap_or("A table component")))
+        }
+    })?;
+
+    Ok((copied, tables))
+}
+
+fn unsupported(name : &str) -> Error {
+
+    err_msg(format!("{} doesn't support snapshots. Implement Snapshot for it and register it with GameState::register_snapshot.", name))
+}
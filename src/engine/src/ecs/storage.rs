@@ -0,0 +1,492 @@
+//! STORAGE
+//! Components live in one of two kinds of storage, picked per component type through
+//! Component::STORAGE:
+//!
+//! Sparse - each type gets its own GenerationalIndexArray. Adding and removing is cheap, so this is
+//! the default and the right choice for components which come and go (e.g. a selection marker).
+//!
+//! Table - every entity with the same set of table components shares a table, where each component
+//! type is a contiguous column. Iterating is cache friendly, but adding or removing a table
+//! component moves the entity's whole row into another table. Best for components which nearly
+//! every entity has for its whole life (transforms, colors, render data).
+//!
+//! Adding or removing a table component moves the entity's row, which would pull it out from under
+//! a query iterating over the table, so systems do it through commands.
+
+use std::any::{Any, TypeId, type_name};
+use std::collections::HashMap;
+use failure::Error;
+use crate::generational_index::generational_index::{GenerationalIndex, ComponentTicks};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StorageType {
+
+    Sparse,
+    Table
+}
+
+/// A type-erased table column.
+pub trait Column : Any {
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the value in the row. The last value is swapped into its place.
+    fn swap_remove(&mut self, row : usize);
+
+    /// Moves the value in the row to the end of another column of the same type. The last value is
+    /// swapped into its place.
+    fn move_row(&mut self, row : usize, to : &mut dyn Column);
+
+    /// A new, empty column of the same type.
+    fn empty(&self) -> Box<dyn Column>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// A column of T values along with their change ticks.
+pub struct TypedColumn<T> {
+
+    pub values : Vec<T>,
+    pub ticks : Vec<ComponentTicks>
+}
+
+impl<T> Default for TypedColumn<T> {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TypedColumn<T> {
+
+    pub fn new() -> TypedColumn<T> {
+
+        TypedColumn { values : Vec::new(), ticks : Vec::new() }
+    }
+
+    /// Copies the column using the given function to copy each value. Every copied value counts as
+    /// added on the given tick.
+    pub fn copy_with<F, E>(&self, copy : F, tick : u64) -> Result<TypedColumn<T>, E> where F : FnMut(&T) -> Result<T, E> {
+
+        Ok(TypedColumn {
+            values : self.values.iter().map(copy).collect::<Result<Vec<T>, E>>()?,
+            ticks : vec![ComponentTicks::new(tick); self.ticks.len()]
+        })
+    }
+}
+
+impl<T : 'static> Column for TypedColumn<T> {
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn swap_remove(&mut self, row : usize) {
+
+        self.values.swap_remove(row);
+        self.ticks.swap_remove(row);
+    }
+
+    fn move_row(&mut self, row : usize, to : &mut dyn Column) {
+
+        let to = to.as_any_mut().downcast_mut::<TypedColumn<T>>().unwrap();
+
+        to.values.push(self.values.swap_remove(row));
+        to.ticks.push(self.ticks.swap_remove(row));
+    }
+
+    fn empty(&self) -> Box<dyn Column> {
+        Box::new(TypedColumn::<T>::new())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Every entity which has exactly the same set of table components. Row n of every column belongs
+/// to entities[n].
+pub struct Table {
+
+    types : Vec<TypeId>,
+    columns : HashMap<TypeId, Box<dyn Column>>,
+    entities : Vec<GenerationalIndex>
+}
+
+impl Table {
+
+    fn column<T : 'static>(&self) -> Option<&TypedColumn<T>> {
+
+        self.columns.get(&TypeId::of::<T>())?.as_any().downcast_ref::<TypedColumn<T>>()
+    }
+
+    fn column_mut<T : 'static>(&mut self) -> Option<&mut TypedColumn<T>> {
+
+        self.columns.get_mut(&TypeId::of::<T>())?.as_any_mut().downcast_mut::<TypedColumn<T>>()
+    }
+
+    pub fn entities(&self) -> &Vec<GenerationalIndex> {
+        &self.entities
+    }
+
+    /// True if the table has a column for the type.
+    pub fn has_column(&self, type_id : &TypeId) -> bool {
+        self.columns.contains_key(type_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// Every table, plus where each entity's row is.
+pub struct Tables {
+
+    tables : Vec<Table>,
+    lookup : HashMap<Vec<TypeId>, usize>,
+    locations : Vec<Option<(GenerationalIndex, usize, usize)>>,
+    names : HashMap<TypeId, &'static str>,
+    removed : HashMap<TypeId, Vec<(GenerationalIndex, u64)>>
+}
+
+impl Default for Tables {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tables {
+
+    pub fn new() -> Tables {
+
+        Tables {
+            tables : Vec::new(),
+            lookup : HashMap::new(),
+            locations : Vec::new(),
+            names : HashMap::new(),
+            removed : HashMap::new()
+        }
+    }
+
+    /// The table and row holding the entity's table components, if it has any.
+    pub fn location(&self, entity : &GenerationalIndex) -> Option<(usize, usize)> {
+
+        match self.locations.get(entity.index()) {
+            Some(Some((owner, table, row))) if owner.generation == entity.generation => Some((*table, *row)),
+            _ => None
+        }
+    }
+
+    pub fn get<T : 'static>(&self, entity : &GenerationalIndex) -> Option<&T> {
+
+        let (table, row) = self.location(entity)?;
+
+        self.tables[table].column::<T>()?.values.get(row)
+    }
+
+    pub fn get_mut_with_ticks<T : 'static>(&mut self, entity : &GenerationalIndex) -> Option<(&mut T, &mut ComponentTicks)> {
+
+        let (table, row) = self.location(entity)?;
+
+        let column = self.tables[table].column_mut::<T>()?;
+
+        Some((&mut column.values[row], &mut column.ticks[row]))
+    }
+
+    /// Where the T column's values and ticks start in each table, or None for tables without one.
+    /// Lets a query hand out rows from several columns at once without borrowing the tables for
+    /// each one. The pointers are only valid until the tables are next changed.
+    pub fn column_ptrs<T : 'static>(&mut self) -> Vec<Option<(*mut T, *mut ComponentTicks)>> {
+
+        self.tables.iter_mut()
+            .map(|table| table.column_mut::<T>().map(|column| (column.values.as_mut_ptr(), column.ticks.as_mut_ptr())))
+            .collect()
+    }
+
+    pub fn ticks<T : 'static>(&self, entity : &GenerationalIndex) -> Option<&ComponentTicks> {
+
+        let (table, row) = self.location(entity)?;
+
+        self.tables[table].column::<T>()?.ticks.get(row)
+    }
+
+    pub fn contains<T : 'static>(&self, entity : &GenerationalIndex) -> bool {
+
+        self.contains_type(entity, &TypeId::of::<T>())
+    }
+
+    pub fn contains_type(&self, entity : &GenerationalIndex, type_id : &TypeId) -> bool {
+
+        match self.location(entity) {
+            Some((table, _)) => self.tables[table].columns.contains_key(type_id),
+            None => false
+        }
+    }
+
+    /// Adds a component to the entity, moving it into the table for its new set of components.
+    /// Replacing a component the entity already has doesn't move it, and counts as a change. A row
+    /// left behind by an older generation of the same index is dropped first, but a stale index
+    /// never touches the row of a newer generation - the value is dropped and false is returned.
+    pub fn insert<T : 'static>(&mut self, entity : &GenerationalIndex, value : T, tick : u64) -> bool {
+
+        match self.locations.get(entity.index()) {
+            Some(Some((owner, _, _))) if owner.generation > entity.generation => return false,
+            Some(Some((owner, _, _))) if owner.generation < entity.generation => {
+                let owner = *owner;
+                self.remove_entity(&owner, tick);
+            },
+            _ => {}
+        }
+
+        let location = self.location(entity);
+
+        if let Some((table, row)) = location {
+
+            if let Some(column) = self.tables[table].column_mut::<T>() {
+
+                column.values[row] = value;
+                column.ticks[row].changed = tick;
+
+                return true
+            }
+        }
+
+        self.names.insert(TypeId::of::<T>(), type_name::<T>());
+
+        let mut types = location.map_or(Vec::new(), |(table, _)| self.tables[table].types.clone());
+
+        types.push(TypeId::of::<T>());
+        types.sort();
+
+        let target = self.table_for(types, location.map(|(table, _)| table), Some(Box::new(TypedColumn::<T>::new())));
+
+        match location {
+            Some((table, row)) => self.move_entity(entity, table, row, target, None),
+            None => self.push_entity(entity, target)
+        }
+
+        let column = self.tables[target].column_mut::<T>().unwrap();
+
+        column.values.push(value);
+        column.ticks.push(ComponentTicks::new(tick));
+
+        true
+    }
+
+    /// Removes and returns a component, moving the entity into the table for the components it has
+    /// left.
+    pub fn remove<T : 'static>(&mut self, entity : &GenerationalIndex, tick : u64) -> Option<T> {
+
+        let (table, row) = self.location(entity)?;
+
+        let value = {
+            let column = self.tables[table].column_mut::<T>()?;
+
+            column.ticks.swap_remove(row);
+            column.values.swap_remove(row)
+        };
+
+        self.removed.entry(TypeId::of::<T>()).or_default().push((*entity, tick));
+
+        let types = self.tables[table].types.iter()
+            .filter(|type_id| **type_id != TypeId::of::<T>())
+            .cloned()
+            .collect::<Vec<TypeId>>();
+
+        if types.is_empty() {
+            self.remove_row(table, row);
+            self.locations[entity.index()] = None;
+        } else {
+            let target = self.table_for(types, Some(table), None);
+            self.move_entity(entity, table, row, target, Some(TypeId::of::<T>()));
+        }
+
+        Some(value)
+    }
+
+    /// Drops every table component the entity has.
+    pub fn remove_entity(&mut self, entity : &GenerationalIndex, tick : u64) {
+
+        let (table, row) = match self.location(entity) {
+            Some(location) => location,
+            None => return
+        };
+
+        for type_id in self.tables[table].types.clone() {
+            self.removed.entry(type_id).or_default().push((*entity, tick));
+        }
+
+        for column in self.tables[table].columns.values_mut() {
+            column.swap_remove(row);
+        }
+
+        self.remove_row(table, row);
+        self.locations[entity.index()] = None;
+    }
+
+    /// The names of every table component the entity has.
+    pub fn type_names(&self, entity : &GenerationalIndex) -> Vec<&'static str> {
+
+        match self.location(entity) {
+            Some((table, _)) => self.tables[table].types.iter().map(|type_id| self.names[type_id]).collect(),
+            None => Vec::new()
+        }
+    }
+
+    /// The names of every component type which has ever been stored in a table.
+    pub fn names(&self) -> Vec<&'static str> {
+
+        self.names.values().cloned().collect()
+    }
+
+    pub fn tables(&self) -> &Vec<Table> {
+        &self.tables
+    }
+
+    /// Copies every table, using the given function to copy each column. Entities keep the same
+    /// rows in the copy. No removals are carried over.
+    pub fn copy_with<F>(&self, mut copy : F) -> Result<Tables, Error>
+        where F : FnMut(&TypeId, &dyn Column) -> Result<Box<dyn Column>, Error> {
+
+        let mut tables = Vec::with_capacity(self.tables.len());
+
+        for table in self.tables.iter() {
+
+            let mut columns = HashMap::new();
+
+            for (type_id, column) in table.columns.iter() {
+                columns.insert(*type_id, copy(type_id, column.as_ref())?);
+            }
+
+            tables.push(Table { types : table.types.clone(), columns, entities : table.entities.clone() });
+        }
+
+        Ok(Tables {
+            tables,
+            lookup : self.lookup.clone(),
+            locations : self.locations.clone(),
+            names : self.names.clone(),
+            removed : HashMap::new()
+        })
+    }
+
+    /// The name of a component type which has been stored in a table.
+    pub fn name_of(&self, type_id : &TypeId) -> Option<&'static str> {
+
+        self.names.get(type_id).cloned()
+    }
+
+    /// Every entity which lost a T table component after the given tick.
+    pub fn removed_since<T : 'static>(&self, since : u64) -> Vec<GenerationalIndex> {
+
+        match self.removed.get(&TypeId::of::<T>()) {
+            Some(removed) => removed.iter().filter(|(_, tick)| *tick > since).map(|(entity, _)| *entity).collect(),
+            None => Vec::new()
+        }
+    }
+
+    /// Forgets every removal recorded on or before the given tick.
+    pub fn prune_removed(&mut self, before : u64) {
+
+        for removed in self.removed.values_mut() {
+            removed.retain(|(_, tick)| *tick > before);
+        }
+    }
+
+    /// Finds the table for a set of component types, creating it if it doesn't exist. New columns
+    /// are copied from the entity's current table, plus the column for any newly added type.
+    fn table_for(&mut self, types : Vec<TypeId>, from : Option<usize>, added : Option<Box<dyn Column>>) -> usize {
+
+        if let Some(table) = self.lookup.get(&types) {
+            return *table
+        }
+
+        let mut added = added;
+        let mut columns = HashMap::new();
+
+        for type_id in types.iter() {
+
+            let column = match from.and_then(|from| self.tables[from].columns.get(type_id)) {
+                Some(column) => column.empty(),
+                None => added.take().unwrap()
+            };
+
+            columns.insert(*type_id, column);
+        }
+
+        self.tables.push(Table { types : types.clone(), columns, entities : Vec::new() });
+        self.lookup.insert(types, self.tables.len() - 1);
+
+        self.tables.len() - 1
+    }
+
+    fn push_entity(&mut self, entity : &GenerationalIndex, table : usize) {
+
+        self.tables[table].entities.push(*entity);
+
+        if entity.index() >= self.locations.len() {
+            self.locations.resize_with(entity.index() + 1, || None);
+        }
+
+        self.locations[entity.index()] = Some((*entity, table, self.tables[table].entities.len() - 1));
+    }
+
+    /// Moves every column value in the row into the target table, dropping any the target doesn't
+    /// have. The skipped column has already had its value taken out.
+    fn move_entity(&mut self, entity : &GenerationalIndex, from : usize, row : usize, to : usize, skip : Option<TypeId>) {
+
+        let (source, target) = if from < to {
+            let (left, right) = self.tables.split_at_mut(to);
+            (&mut left[from], &mut right[0])
+        } else {
+            let (left, right) = self.tables.split_at_mut(from);
+            (&mut right[0], &mut left[to])
+        };
+
+        for (type_id, column) in source.columns.iter_mut() {
+
+            if Some(*type_id) == skip {
+                continue
+            }
+
+            match target.columns.get_mut(type_id) {
+                Some(target_column) => column.move_row(row, target_column.as_mut()),
+                None => column.swap_remove(row)
+            }
+        }
+
+        self.remove_row(from, row);
+        self.push_entity(entity, to);
+    }
+
+    /// Removes an entity from a table's entity list once its column values are gone, fixing up the
+    /// location of the entity swapped into its row.
+    fn remove_row(&mut self, table : usize, row : usize) {
+
+        let entities = &mut self.tables[table].entities;
+
+        entities.swap_remove(row);
+
+        if row < entities.len() {
+
+            let moved = entities[row];
+
+            self.locations[moved.index()] = Some((moved, table, row));
+        }
+    }
+}
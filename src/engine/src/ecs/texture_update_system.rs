@@ -1,6 +1,5 @@
 use crate::ecs::{TextureMixComponent, TextureUpdateComponent};
 use failure::Error;
-use crate::generational_index::generational_index::{GenerationalIndex};
 use crate::game_state::GameState;
 use crate::ecs::system::System;
 
@@ -8,39 +7,65 @@ pub struct TextureUpdateSystem;
 
 impl<'a> System<'a> for TextureUpdateSystem {
 
-    type SystemInput = (&'a mut GameState);
+    type SystemInput = &'a mut GameState;
 
     fn run(input: Self::SystemInput) -> Result<(), Error> {
 
-        let size = input.get_map::<TextureUpdateComponent>().entries.len();
+        let query = input.query::<(&mut TextureUpdateComponent, Option<&mut TextureMixComponent>)>();
 
-        let mut opacity: gl::types::GLfloat;
+        for (mut change, texture) in query {
 
-        for index in 0..size {
+            let opacity: gl::types::GLfloat = change.opacity_change;
 
-
-            let gen_index : GenerationalIndex;
-
-            {
-                let updates = &mut input.get_map_mut::<TextureUpdateComponent>();
-
-                if let Some(change) = updates.entries[index].as_mut() {
-                    opacity = change.value.opacity_change;
-                    gen_index = change.owned_entity;
-                    change.value.opacity_change = 0.0;
-                } else {
-                    continue
-                }
+            // Only written to when there is something to apply, so Changed<TextureUpdateComponent>
+            // still picks out the entities with a new change.
+            if opacity == 0.0 {
+                continue;
             }
 
+            change.opacity_change = 0.0;
 
-
-            let textures = input.get_map_mut::<TextureMixComponent>();
-
-            if let Some(texture) = textures.get_mut(&gen_index) {
+            if let Some(mut texture) = texture {
                 texture.opacity += opacity
             }
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ecs::change_detection::{Changed, set_last_run_tick};
+    use crate::game_state::Entity;
+
+    #[test]
+    fn updates_without_a_change_are_left_untouched() {
+
+        let mut state = GameState::create_initial_state();
+
+        let idle = GameState::create_entity(&mut state)
+            .with(TextureUpdateComponent { opacity_change : 0.0 })
+            .build();
+
+        let fading = GameState::create_entity(&mut state)
+            .with(TextureUpdateComponent { opacity_change : 0.5 })
+            .build();
+
+        let tick = state.increment_change_tick();
+        set_last_run_tick(tick);
+        state.increment_change_tick();
+
+        TextureUpdateSystem::run(&mut state).unwrap();
+
+        let changed = state.query::<(Entity, Changed<TextureUpdateComponent>)>()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<Entity>>();
+
+        set_last_run_tick(0);
+
+        assert_eq!(changed, vec![fading]);
+        assert!(!changed.contains(&idle));
+    }
+}
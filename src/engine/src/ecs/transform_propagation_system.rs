@@ -0,0 +1,83 @@
+use crate::ecs::system::System;
+use crate::ecs::{PositionComponent, PreviousPositionComponent, RotationComponent, ScaleComponent, ParentComponent,
+                 ChildrenComponent, GlobalTransformComponent};
+use crate::ecs::query::{With, Without};
+use crate::game_state::{GameState, Entity};
+use crate::time::Time;
+use failure::Error;
+use nalgebra::{Matrix4, Vector3};
+
+/// Walks every hierarchy from its root down, computing each entity's GlobalTransformComponent from
+/// its parent's global transform and its own position, rotation and scale. Moving entities use a
+/// position part way between their last two fixed steps (see Time::alpha), so children follow their
+/// parents smoothly too.
+pub struct TransformPropagationSystem;
+
+impl<'a> System<'a> for TransformPropagationSystem {
+
+    type SystemInput = &'a mut GameState;
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let alpha = input.try_resource::<Time>().map_or(1.0, |time| time.alpha());
+
+        let roots = input.query::<(Entity, With<PositionComponent>, Without<ParentComponent>)>()
+            .map(|(entity, _, _)| entity)
+            .collect::<Vec<Entity>>();
+
+        for root in roots.iter() {
+            TransformPropagationSystem::propagate(input, root, Matrix4::identity(), alpha);
+        }
+
+        Ok(())
+    }
+}
+
+impl TransformPropagationSystem {
+
+    fn propagate(state : &mut GameState, entity : &Entity, parent : Matrix4<f32>, alpha : f32) {
+
+        let matrix = parent * local_transform(state, entity, alpha);
+
+        let current = state.get::<GlobalTransformComponent>(entity).map(|global| global.matrix);
+
+        // Left alone when it hasn't moved, so it only shows up as changed when it has. A new global
+        // transform moves the entity to another table, so it goes through commands.
+        match current {
+            Some(current) if current == matrix => (),
+            Some(_) => state.get_mut::<GlobalTransformComponent>(entity).unwrap().matrix = matrix,
+            None => state.commands.insert(*entity, GlobalTransformComponent { matrix })
+        }
+
+        let children = state.get::<ChildrenComponent>(entity).map_or(Vec::new(), |children| children.children.clone());
+
+        for child in children.iter() {
+            TransformPropagationSystem::propagate(state, child, matrix, alpha);
+        }
+    }
+}
+
+/// Builds an entity's model matrix relative to its parent (or the world, if it has none). A missing
+/// position or rotation counts as zero and a missing scale as one. The position is interpolated by
+/// alpha (see interpolated_position).
+pub fn local_transform(state : &GameState, entity : &Entity, alpha : f32) -> Matrix4<f32> {
+
+    let position = interpolated_position(state.get::<PositionComponent>(entity), state.get::<PreviousPositionComponent>(entity), alpha);
+    let rotation = state.get::<RotationComponent>(entity).map_or(Vector3::new(0.0, 0.0, 0.0), |rotation| rotation.rotation);
+    let scale = state.get::<ScaleComponent>(entity).map_or(Vector3::new(1.0, 1.0, 1.0), |scale| scale.scale);
+
+    Matrix4::new_translation(&position) * Matrix4::from_scaled_axis(rotation) * Matrix4::new_nonuniform_scaling(&scale)
+}
+
+/// The position to draw an entity at. alpha is how far the world is between the last two fixed
+/// steps, from 0.0 (the previous position) to 1.0 (the current one). Entities without a previous
+/// position are drawn where they are, and a missing position counts as zero.
+pub fn interpolated_position(position : Option<&PositionComponent>, previous : Option<&PreviousPositionComponent>,
+                             alpha : f32) -> Vector3<f32> {
+
+    match (position, previous) {
+        (Some(position), Some(previous)) => previous.position + (position.position - previous.position) * alpha,
+        (Some(position), None) => position.position,
+        _ => Vector3::new(0.0, 0.0, 0.0)
+    }
+}
@@ -0,0 +1,106 @@
+//! EVENT CHANNELS
+//! A typed, double-buffered queue of events stored as a world resource. Systems send events into the
+//! current buffer; at the start of every frame the buffers swap, so an event stays readable for the
+//! rest of the frame it was sent on and all of the next one, then it is dropped. This lets a system
+//! react to something which happened in an earlier stage (or later in the previous frame) without
+//! knowing who sent it.
+//!
+//! Every event gets an increasing id. Readers remember the id of the last event they saw, so each
+//! reader sees each event exactly once no matter how many other readers there are.
+
+use std::marker::PhantomData;
+
+pub struct Events<T> {
+
+    previous : Vec<(usize, T)>,
+    current : Vec<(usize, T)>,
+    event_count : usize
+}
+
+impl<T> Default for Events<T> {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Events<T> {
+
+    pub fn new() -> Events<T> {
+
+        Events { previous : Vec::new(), current : Vec::new(), event_count : 0 }
+    }
+
+    pub fn send(&mut self, event : T) {
+
+        self.current.push((self.event_count, event));
+        self.event_count += 1;
+    }
+
+    /// Swaps the buffers, dropping every event sent before the last update. Called once per frame.
+    pub fn update(&mut self) {
+
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// Drops every event in both buffers. Readers are unaffected.
+    pub fn clear(&mut self) {
+
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    /// Every event still held, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+
+        self.previous.iter().chain(self.current.iter()).map(|(_, event)| event)
+    }
+
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+}
+
+/// Reads events from an Events<T> channel, remembering where it got up to. Systems usually own one
+/// of these inside their closure, e.g:
+///
+/// let mut reader = EventReader::<CollisionEvent>::new();
+/// SystemDescriptor::new("select", move |state| SelectSystem::run((state, &mut reader)))
+pub struct EventReader<T> {
+
+    last_event_count : usize,
+    marker : PhantomData<fn() -> T>
+}
+
+impl<T> Default for EventReader<T> {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> EventReader<T> {
+
+    pub fn new() -> EventReader<T> {
+
+        EventReader { last_event_count : 0, marker : PhantomData }
+    }
+
+    /// Every event sent since this reader last read, oldest first. Events which were dropped before
+    /// the reader got to them are missed.
+    pub fn read<'a>(&mut self, events : &'a Events<T>) -> impl Iterator<Item = &'a T> {
+
+        let since = self.last_event_count;
+
+        self.last_event_count = events.event_count;
+
+        events.previous.iter()
+            .chain(events.current.iter())
+            .filter(move |(id, _)| *id >= since)
+            .map(|(_, event)| event)
+    }
+}
@@ -1,4 +1,5 @@
 pub mod window_event;
+pub mod event_channel;
 
 
 
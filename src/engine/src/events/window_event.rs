@@ -2,8 +2,11 @@ use std::collections::VecDeque;
 use crate::platform::windows::windows_window;
 use crate::platform::windows::windows_window::WindowsWindow;
 
+/// A change to make to the window once the frame's events have been processed.
+pub type WindowCallback = Box<dyn FnMut(&mut WindowsWindow)>;
+
 pub struct WindowEvent<'a> {
 
     pub window : &'a mut windows_window::WindowsWindow,
-    pub events : &'a mut VecDeque<Box<dyn FnMut(&mut WindowsWindow)>>
+    pub events : &'a mut VecDeque<WindowCallback>
 }
\ No newline at end of file
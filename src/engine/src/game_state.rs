@@ -1,228 +1,764 @@
-use crate::ecs::{ColorComponent, PositionComponent, Component, TextureMixComponent, Texture,
-                 RenderComponent, TextureUpdateComponent, VelocityComponent, ScaleComponent,
-                 OrthographicCameraComponent, BoxCollider2DComponent, SelectedComponent,
-                 RotationComponent, RotationUpdateComponent, LookAtPositionComponent};
+use crate::ecs::{Component, ParentComponent, ChildrenComponent, NameComponent,
+                 TagsComponent};
 use crate::generational_index::generational_index::*;
 use anymap::AnyMap;
-use failure::Error;
-use nalgebra::{Vector3, Matrix4, Vector2};
-use crate::platform::windows::windows_window::WindowsWindow;
-use crate::utilities::vector_utils;
+use failure::{Error, err_msg};
+use std::any::{TypeId, type_name};
+use std::collections::HashMap;
+use crate::ecs::query::{Fetch, Query, validate_access};
+use crate::ecs::registry::{ComponentRegistry, ReflectInfo, ComponentHook};
+use crate::ecs::hooks;
+use crate::ecs::change_detection::last_run_tick;
+use crate::ecs::commands::Commands;
+use crate::ecs::storage::{StorageType, Tables};
+use crate::ecs::snapshot::{self, Snapshot, WorldSnapshot};
+use crate::ecs::reflect::{self, Reflect, FieldInfo};
+use crate::events::event_channel::Events;
+use crate::scene::{Scene, SceneComponent};
+use crate::prefab::Prefabs;
+use crate::history::{self, History, Edit};
+use serde_json::Value;
 
 /// Types for the generational indices and arrays.
-type Entity = GenerationalIndex;
-type EntityMap<T> = GenerationalIndexArray<T>;
+pub type Entity = GenerationalIndex;
+pub type EntityMap<T> = GenerationalIndexArray<T>;
 
 /// GameState object stores all entities and ecs within itself. If handles the streaming of
 /// ecs into different systems.
-
 pub struct GameState {
 
     pub components : AnyMap,
+    pub tables : Tables,
     pub allocator : GenerationalIndexAllocator,
-    pub entities : Vec<Entity>
+    pub registry : ComponentRegistry,
+    pub resources : AnyMap,
+    pub change_tick : u64,
+    pub event_updates : Vec<fn(&mut GameState)>,
+    pub commands : Commands,
+    pub names : HashMap<String, Entity>
 }
 
 /// should store all ecs and entity IDs when actual gameobjects and players are added to the game.
-
 impl GameState {
 
     /// Basic constructor for the game state.
     pub fn create_initial_state() -> GameState {
 
-        let state = GameState {
+        let mut state = GameState {
             components : AnyMap::new(),
+            tables : Tables::new(),
             allocator : GenerationalIndexAllocator::new(),
-            entities : Vec::new()
+            registry : ComponentRegistry::new(),
+            resources : AnyMap::new(),
+            change_tick : 1,
+            event_updates : Vec::new(),
+            commands : Commands::new(),
+            names : HashMap::new()
         };
 
+        snapshot::register_engine_components(&mut state);
+        reflect::register_engine_components(&mut state);
+        hooks::register_engine_hooks(&mut state);
+
         state
     }
 
-    /// Takes in a generic component and attempts to map it to a type in the component anymap.
+    /// Takes in a generic component and stores it in the component's storage. Sparse components go
+    /// into the component anymap, where the map is created the first time a component of that type is
+    /// inserted. Table components move the entity into the table for its new set of components.
+    /// Replacing a component runs the on_remove hooks for the old value, then the on_add hooks for
+    /// the new one. Returns false, dropping the component, if the entity has been despawned.
+    pub fn register_component<T : Component>(&mut self, component : T, index : &GenerationalIndex) -> bool {
 
-    pub fn register_component<T : Component>(&mut self, component : T, index : &GenerationalIndex) {
+        if !self.is_alive(index) {
+            return false
+        }
 
-        if let Some(m) = self.components.get_mut::<EntityMap<T>>() {
+        let tick = self.change_tick;
+        let hooks = self.registry.hooks(&TypeId::of::<T>());
 
-            GameState::sync_registry(&self.entities, m);
+        if !hooks.on_remove.is_empty() && self.has::<T>(index) {
+            self.run_hooks(&hooks.on_remove, index);
+        }
 
-            m.set(index, component);
+        let added = match T::STORAGE {
+            StorageType::Sparse => self.get_map_mut::<T>().set(index, component, tick),
+            StorageType::Table => self.tables.insert(index, component, tick)
+        };
 
-        } else {
-            eprintln!("The component does not exist!");
+        if added {
+            self.run_hooks(&hooks.on_add, index);
         }
+
+        added
     }
 
-    pub fn add_component_to<T: Component>(&mut self, component : T, index : &Entity) {
+    pub fn add_component_to<T: Component>(&mut self, component : T, index : &Entity) -> bool {
 
-        self.register_component(component, index);
+        self.register_component(component, index)
     }
 
+    /// Removes a component, running its on_remove hooks first so they can still read it.
     pub fn remove_component<T : Component>(&mut self, index : &Entity) {
 
-        if let Some(array) = self.components.get_mut::<EntityMap<T>>() {
-            //println!("Removing");
-            array.remove(&index);
-        } else {
-            eprintln!("The component does not exist!");
+        if !self.has::<T>(index) {
+            return
         }
+
+        let hooks = self.registry.hooks(&TypeId::of::<T>());
+
+        self.run_hooks(&hooks.on_remove, index);
+
+        let tick = self.change_tick;
+
+        match T::STORAGE {
+            StorageType::Sparse => if let Some(array) = self.try_get_map_mut::<T>() {
+                array.remove(index, tick);
+            },
+            StorageType::Table => { self.tables.remove::<T>(index, tick); }
+        }
+    }
+
+    /// Adds a hook which runs whenever a T is added to an entity, including when it replaces an
+    /// existing T. The new component is already in place when the hook runs.
+    /// SEE: hooks
+    pub fn on_add<T : Component>(&mut self, hook : ComponentHook) {
+
+        self.registry.add_on_add::<T>(hook);
     }
 
-    /// used to register a component array to the anymap
+    /// Adds a hook which runs whenever a T is removed from an entity, replaced, or despawned along
+    /// with its entity. The old component is still in place when the hook runs.
+    pub fn on_remove<T : Component>(&mut self, hook : ComponentHook) {
+
+        self.registry.add_on_remove::<T>(hook);
+    }
 
+    fn run_hooks(&mut self, hooks : &[ComponentHook], index : &Entity) {
+
+        for hook in hooks.iter() {
+            hook(self, index);
+        }
+    }
+
+    /// True if the entity has a component of the given type, in either kind of storage.
+    pub fn has_type(&self, index : &Entity, type_id : &TypeId) -> bool {
+
+        match self.registry.get(type_id) {
+            Some(info) if (info.contains)(&self.components, index) => true,
+            _ => self.tables.contains_type(index, type_id)
+        }
+    }
+
+    /// used to register a component array to the anymap. Only needed to pre-size or replace a map,
+    /// since maps are otherwise created on first insert.
     pub fn register_map<T : 'static>(&mut self, component : GenerationalIndexArray<T>) {
 
         self.components.insert(component);
+        self.registry.register::<T>();
     }
 
-    /// Allocates a generational index and adds it to the entity vector
-
-    pub fn create_entity(state : &mut GameState) -> EntityBuilder {
+    /// Allocates a generational index for a new entity.
+    pub fn create_entity(state : &mut GameState) -> EntityBuilder<'_> {
 
         let entity = state.allocator.allocate();
 
-        let idx = entity.index();
+        EntityBuilder::new(entity, state)
+    }
+
+    /// Returns true if the index refers to an entity which has not been despawned.
+    pub fn is_alive(&self, index : &Entity) -> bool {
+
+        self.allocator.is_live(index)
+    }
+
+    /// Every living entity, in index order.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+
+        self.allocator.iter()
+    }
+
+    pub fn entity_count(&self) -> usize {
+
+        self.allocator.len()
+    }
+
+    /// Destroys an entity. Every component it owns is removed from every registered map and its
+    /// index is handed back to the allocator. It is detached from
+    /// its parent, and its children are left without one - use despawn_recursive to take them too.
+    /// Returns false if the entity was already dead.
+    pub fn despawn(&mut self, index : &Entity) -> bool {
+
+        if !self.is_alive(index) {
+            return false
+        }
+
+        self.remove_parent(index);
+        self.remove_name(index);
+
+        for child in self.children_of(index).iter() {
+            self.remove_component::<ParentComponent>(child);
+        }
+
+        for type_id in self.registry.types_with_on_remove() {
+
+            if self.has_type(index, &type_id) {
+                let hooks = self.registry.hooks(&type_id);
+                self.run_hooks(&hooks.on_remove, index);
+            }
+        }
+
+        for info in self.registry.iter() {
+            (info.remove)(&mut self.components, index, self.change_tick);
+        }
+
+        self.tables.remove_entity(index, self.change_tick);
+
+        self.allocator.deallocate(index)
+    }
+
+    /// Despawns an entity along with all of its children, their children and so on.
+    /// Returns the number of entities despawned.
+    pub fn despawn_recursive(&mut self, index : &Entity) -> usize {
+
+        let despawned = self.children_of(index).iter()
+            .map(|child| self.despawn_recursive(child))
+            .sum::<usize>();
+
+        despawned + self.despawn(index) as usize
+    }
+
+    /// Attaches the child to the parent, detaching it from any parent it already had. From then on
+    /// the child's transform is relative to the parent's. Fails if either entity is dead or the
+    /// child is already an ancestor of the parent.
+    pub fn set_parent(&mut self, child : &Entity, parent : &Entity) -> Result<(), Error> {
+
+        if !self.is_alive(child) || !self.is_alive(parent) {
+            return Err(err_msg("Can't parent a despawned entity."))
+        }
+
+        let mut ancestor = Some(*parent);
+
+        while let Some(entity) = ancestor {
+
+            if entity == *child {
+                return Err(err_msg("An entity can't be parented to itself or one of its descendants."))
+            }
+
+            ancestor = self.get::<ParentComponent>(&entity).map(|parent| parent.parent);
+        }
+
+        self.remove_parent(child);
 
-        if idx < state.entities.len() {
+        self.add_component_to(ParentComponent { parent : *parent }, child);
 
-            state.entities[idx] = entity;
+        match self.get_mut::<ChildrenComponent>(parent) {
+            Some(children) => children.children.push(*child),
+            None => { self.add_component_to(ChildrenComponent { children : vec![*child] }, parent); }
+        }
+
+        Ok(())
+    }
 
-        } else {
-            state.entities.push(entity);
+    /// Detaches the entity from its parent, if it has one. Its transform becomes relative to the
+    /// world again.
+    pub fn remove_parent(&mut self, child : &Entity) {
+
+        let parent = match self.get::<ParentComponent>(child) {
+            Some(parent) => parent.parent,
+            None => return
+        };
+
+        self.remove_component::<ParentComponent>(child);
+
+        if let Some(children) = self.get_mut::<ChildrenComponent>(&parent) {
+            children.children.retain(|entity| entity != child);
         }
+    }
+
+    /// The entity's direct children.
+    pub fn children_of(&self, index : &Entity) -> Vec<Entity> {
+
+        self.get::<ChildrenComponent>(index).map_or(Vec::new(), |children| children.children.clone())
+    }
+
+    /// Names the entity, replacing any name it already had. Fails if another living entity already
+    /// has the name.
+    pub fn set_name(&mut self, index : &Entity, name : &str) -> Result<(), Error> {
+
+        match self.find_by_name(name) {
+            Some(entity) if entity == *index => return Ok(()),
+            Some(_) => return Err(err_msg(format!("An entity named {} already exists.", name))),
+            None => ()
+        }
+
+        // The name index is kept up to date by NameComponent's hooks.
+        self.add_component_to(NameComponent { name : String::from(name) }, index);
+
+        Ok(())
+    }
 
-        let new_idx = state.entities[idx];
+    pub fn remove_name(&mut self, index : &Entity) {
 
-        EntityBuilder::new(new_idx, state)
+        self.remove_component::<NameComponent>(index);
     }
 
-    /// Returns a mutable reference of the map
+    pub fn name_of(&self, index : &Entity) -> Option<&str> {
 
+        self.get::<NameComponent>(index).map(|name| name.name.as_str())
+    }
+
+    /// Returns the entity with the given name. Every NameComponent is added to the name index when
+    /// it's added to an entity; a name changed in place (e.g. through reflection) falls back to a
+    /// search of every NameComponent.
+    pub fn find_by_name(&self, name : &str) -> Option<Entity> {
+
+        if let Some(entity) = self.names.get(name) {
+
+            if self.name_of(entity) == Some(name) {
+                return Some(*entity)
+            }
+        }
+
+        self.try_get_map::<NameComponent>()?.iter()
+            .find(|(_, component)| component.name == name)
+            .map(|(entity, _)| entity)
+    }
+
+    /// Tags the entity. Adding a tag it already has does nothing.
+    pub fn add_tag(&mut self, index : &Entity, tag : &str) {
+
+        match self.get_mut::<TagsComponent>(index) {
+            Some(tags) => if !tags.tags.iter().any(|existing| existing == tag) {
+                tags.tags.push(String::from(tag))
+            },
+            None => { self.add_component_to(TagsComponent { tags : vec![String::from(tag)] }, index); }
+        }
+    }
+
+    pub fn remove_tag(&mut self, index : &Entity, tag : &str) {
+
+        if let Some(tags) = self.get_mut::<TagsComponent>(index) {
+            tags.tags.retain(|existing| existing != tag);
+        }
+    }
+
+    pub fn has_tag(&self, index : &Entity, tag : &str) -> bool {
+
+        self.get::<TagsComponent>(index).is_some_and(|tags| tags.tags.iter().any(|existing| existing == tag))
+    }
+
+    /// Every entity with the given tag.
+    pub fn entities_with_tag(&self, tag : &str) -> Vec<Entity> {
+
+        match self.try_get_map::<TagsComponent>() {
+            Some(tags) => tags.iter()
+                .filter(|(_, tags)| tags.tags.iter().any(|existing| existing == tag))
+                .map(|(entity, _)| entity)
+                .collect(),
+            None => Vec::new()
+        }
+    }
+
+    /// A short description of the entity for debug output - its name if it has one, followed by its
+    /// index and generation, e.g. "crate (3v0)".
+    pub fn entity_label(&self, index : &Entity) -> String {
+
+        match self.name_of(index) {
+            Some(name) => format!("{} ({:?})", name, index),
+            None => format!("{:?}", index)
+        }
+    }
+
+    /// Despawns every entity which matches the predicate (e.g. everything belonging to a level).
+    /// Returns the number of entities despawned.
+    pub fn despawn_where<F : FnMut(&GameState, &Entity) -> bool>(&mut self, mut predicate : F) -> usize {
+
+        let matching = self.entities()
+            .filter(|entity| predicate(self, entity))
+            .collect::<Vec<Entity>>();
+
+        matching.iter().filter(|entity| self.despawn(entity)).count()
+    }
+
+    /// Returns a mutable reference of the map, creating and registering it if it does not exist.
+    /// Component maps only hold sparse components - table components live in self.tables.
     pub fn get_map_mut<T : 'static>(&mut self) -> &mut EntityMap<T> {
 
+        if !self.components.contains::<EntityMap<T>>() {
+            self.register_map::<T>(EntityMap::new());
+        }
+
         self.components.get_mut::<EntityMap<T>>().unwrap()
     }
 
-    /// Returns an immutable reference of the component map
-
+    /// Returns an immutable reference of the component map.
+    /// Panics if no component of this type has ever been added - use try_get_map to check first.
     pub fn get_map<T : 'static>(&self) -> &EntityMap<T> {
 
-        &*self.components.get::<EntityMap<T>>().unwrap()
+        match self.try_get_map::<T>() {
+            Some(map) => map,
+            None => panic!("No component map exists for {}", type_name::<T>())
+        }
+    }
+
+    /// Returns the component map, or None if no component of this type has been added.
+    pub fn try_get_map<T : 'static>(&self) -> Option<&EntityMap<T>> {
+
+        self.components.get::<EntityMap<T>>()
+    }
+
+    /// Returns the component map mutably, or None if no component of this type has been added.
+    pub fn try_get_map_mut<T : 'static>(&mut self) -> Option<&mut EntityMap<T>> {
+
+        self.components.get_mut::<EntityMap<T>>()
+    }
+
+    /// Returns a single component and marks it as changed.
+    pub fn get_mut<T : Component>(&mut self, index: &Entity) -> Option<&mut T>{
+
+        let tick = self.change_tick;
+        let (component, ticks) = self.get_mut_with_ticks::<T>(index)?;
+
+        ticks.changed = tick;
+
+        Some(component)
     }
 
     /// Returns a single component
-    pub fn get_mut<T : 'static>(&mut self, index: &Entity) -> Option<&mut T>{
+    pub fn get<T : Component>(&self, index: &Entity) -> Option<&T>{
 
-        let mut value = self.get_map_mut::<T>().get_mut(index);
+        match T::STORAGE {
+            StorageType::Sparse => self.try_get_map::<T>()?.get(index),
+            StorageType::Table => self.tables.get::<T>(index)
+        }
+    }
+
+    /// Returns a single component along with its change ticks, without marking it as changed.
+    pub fn get_mut_with_ticks<T : Component>(&mut self, index : &Entity) -> Option<(&mut T, &mut ComponentTicks)> {
 
-        if let Some(_val) = value.as_mut() {
-            return value
-        } else {
-            None
+        match T::STORAGE {
+            StorageType::Sparse => self.try_get_map_mut::<T>()?.get_mut_with_ticks(index),
+            StorageType::Table => self.tables.get_mut_with_ticks::<T>(index)
         }
     }
 
-    /// Returns a single component
-    pub fn get<T : 'static>(&self, index: &Entity) -> Option<&T>{
+    pub fn ticks<T : Component>(&self, index : &Entity) -> Option<&ComponentTicks> {
 
-        //println!("Requesting value of index: {} generation: {}", index.index, index.generation);
-        let value = self.get_map::<T>().get(index);
+        match T::STORAGE {
+            StorageType::Sparse => self.try_get_map::<T>()?.ticks(index),
+            StorageType::Table => self.tables.ticks::<T>(index)
+        }
+    }
 
-        match value {
-            Some(_component) => value,
-            None => None
+    pub fn has<T : Component>(&self, index : &Entity) -> bool {
+
+        match T::STORAGE {
+            StorageType::Sparse => self.try_get_map::<T>().is_some_and(|map| map.contains(index)),
+            StorageType::Table => self.tables.contains::<T>(index)
         }
     }
 
-    /// Ensures that the inputted index array is the same size as the number of entities
-    /// (Each entity can have ONE of each component)
+    /// Every entity which lost its T component (including by being despawned) since the running
+    /// system last ran. Removals are kept for two frames, so every system sees them once.
+    pub fn removed<T : Component>(&self) -> Vec<Entity> {
+
+        match T::STORAGE {
+            StorageType::Sparse => match self.try_get_map::<T>() {
+                Some(map) => map.removed_since(last_run_tick()).collect(),
+                None => Vec::new()
+            },
+            StorageType::Table => self.tables.removed_since::<T>(last_run_tick())
+        }
+    }
+
+    /// Moves the change tick forward. Called by the schedule before each system runs.
+    pub fn increment_change_tick(&mut self) -> u64 {
+
+        self.change_tick += 1;
+        self.change_tick
+    }
+
+    /// Forgets every component removal recorded on or before the given tick.
+    pub fn prune_removed(&mut self, before : u64) {
+
+        for info in self.registry.iter() {
+            (info.prune_removed)(&mut self.components, before);
+        }
+
+        self.tables.prune_removed(before);
+    }
 
-    pub fn sync_registry<T>(entities : &Vec<Entity>, array : &mut GenerationalIndexArray<T>) {
+    /// The names of every component type present in the world.
+    pub fn component_types(&self) -> Vec<&'static str> {
 
-        let entities = entities.len();
+        let mut names = self.registry.names();
 
-        if array.unpacked_entries.len() < entities {
+        names.extend(self.tables.names());
+        names.sort();
 
-            for _entity in array.unpacked_entries.len()..entities {
+        names
+    }
+
+    /// The names of every component type attached to a single entity.
+    pub fn components_of(&self, index : &Entity) -> Vec<&'static str> {
+
+        let mut names = self.registry.iter()
+            .filter(|info| (info.contains)(&self.components, index))
+            .map(|info| info.name)
+            .collect::<Vec<&'static str>>();
+
+        names.extend(self.tables.type_names(index));
+        names.sort();
+
+        names
+    }
+
+    /// Stores a world resource (a singleton such as the camera or input state) which any system can
+    /// reach through the game state. Returns the previous value of the same type, if there was one.
+    pub fn insert_resource<T : 'static>(&mut self, resource : T) -> Option<T> {
+
+        self.resources.insert(resource)
+    }
+
+    pub fn remove_resource<T : 'static>(&mut self) -> Option<T> {
+
+        self.resources.remove::<T>()
+    }
+
+    /// Returns a resource. Panics if it has not been inserted - use try_resource to check first.
+    pub fn resource<T : 'static>(&self) -> &T {
+
+        match self.try_resource::<T>() {
+            Some(resource) => resource,
+            None => panic!("No resource exists for {}", type_name::<T>())
+        }
+    }
 
-                array.set_empty();
+    /// Returns a resource mutably. Panics if it has not been inserted.
+    pub fn resource_mut<T : 'static>(&mut self) -> &mut T {
+
+        match self.try_resource_mut::<T>() {
+            Some(resource) => resource,
+            None => panic!("No resource exists for {}", type_name::<T>())
+        }
+    }
+
+    pub fn try_resource<T : 'static>(&self) -> Option<&T> {
+
+        self.resources.get::<T>()
+    }
+
+    pub fn try_resource_mut<T : 'static>(&mut self) -> Option<&mut T> {
+
+        self.resources.get_mut::<T>()
+    }
+
+    /// Adds an Events<T> resource which is swapped at the start of every frame. Adding the same
+    /// event type twice has no effect.
+    pub fn add_event<T : 'static>(&mut self) {
+
+        if self.try_resource::<Events<T>>().is_some() {
+            return
+        }
+
+        self.insert_resource(Events::<T>::new());
+        self.event_updates.push(swap_event_buffers::<T>);
+    }
+
+    /// Sends an event. Panics if add_event has not been called for T.
+    pub fn send_event<T : 'static>(&mut self, event : T) {
+
+        self.resource_mut::<Events<T>>().send(event);
+    }
+
+    /// Applies every command recorded since the last call, in order. Commands recorded while
+    /// applying are applied too. A failing command doesn't stop the rest from being applied - every
+    /// failure is reported together once the queue is empty.
+    pub fn apply_commands(&mut self) -> Result<(), Error> {
+
+        let mut errors = Vec::new();
+
+        while !self.commands.is_empty() {
+
+            for command in self.commands.take() {
+                if let Err(error) = command(self) {
+                    errors.push(error);
+                }
             }
         }
+
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().unwrap()),
+            count => Err(err_msg(format!("{} commands failed: {}", count,
+                errors.iter().map(|error| error.to_string()).collect::<Vec<String>>().join("; "))))
+        }
+    }
+
+    /// Swaps the buffers of every event channel. Called by the schedule at the start of each frame.
+    pub fn update_events(&mut self) {
+
+        for update in self.event_updates.clone() {
+            update(self);
+        }
     }
 
-    /// A sandbox for experimenting with component creation. The goal is to have entity creation be
-    /// reduced to one or two lines of code.
-
-    pub fn init_test_state(state : &mut GameState, window : &WindowsWindow) -> Result<(Entity), Error>{
-
-        let render_comps : EntityMap<RenderComponent> = EntityMap::new();
-        let pos_comps : EntityMap<PositionComponent> = EntityMap::new();
-        let color_comps : EntityMap<ColorComponent> = EntityMap::new();
-        let texture_comps : EntityMap<TextureMixComponent> = EntityMap::new();
-        let texture_changes : EntityMap<TextureUpdateComponent> = EntityMap::new();
-        let velocity_changes : EntityMap<VelocityComponent> = EntityMap::new();
-        let scales_components : EntityMap<ScaleComponent> = EntityMap::new();
-        let orthographic_cameras : EntityMap<OrthographicCameraComponent> = EntityMap::new();
-        let box_colliders : EntityMap<BoxCollider2DComponent> = EntityMap::new();
-        let selected_components : EntityMap<SelectedComponent> = EntityMap::new();
-        let rotation_components : EntityMap<RotationComponent> = EntityMap::new();
-        let rotation_update_components : EntityMap<RotationUpdateComponent> = EntityMap::new();
-        let look_at_components : EntityMap<LookAtPositionComponent> = EntityMap::new();
-
-        state.register_map(render_comps);
-        state.register_map(pos_comps);
-        state.register_map(color_comps);
-        state.register_map(texture_comps);
-        state.register_map(texture_changes);
-        state.register_map(velocity_changes);
-        state.register_map(scales_components);
-        state.register_map(orthographic_cameras);
-        state.register_map(box_colliders);
-        state.register_map(selected_components);
-        state.register_map(rotation_components);
-        state.register_map(rotation_update_components);
-        state.register_map(look_at_components);
-
-        // RIGHT
-
-        let position = Vector3::new(0.0, 0.0, 0.0);
-        let scale = Vector3::new(50.0, 50.0, 50.0);
-        let corners = vector_utils::get_box_corners(Vector2::new(position.x, position.y), Vector2::new(scale.x * 2.0, scale.y * 2.0));
-
-        let _first_comp = GameState::create_entity(state)
-            .with(RenderComponent {shader_program : triangle_render!(), vertex_array_object : quad!()})
-            .with(PositionComponent {position})
-            .with(RotationComponent { rotation: Vector3::new(0.0, 0.0, 0.0)})
-            .with(ScaleComponent {scale})
-            .with(ColorComponent {color : (1.0, 1.0, 1.0, 0.0) })
-            .with(TextureMixComponent { textures : vec!
-            [texture!("src/engine/src/renderer/textures/container.jpg",0, gl::TEXTURE0, String::from("Texture1")),
-             texture!("src/engine/src/renderer/textures/awesomeface.png",1, gl::TEXTURE1, String::from("Texture2"))],
-                opacity: 0.0})
-            .with(TextureUpdateComponent {opacity_change : 0.0 })
-            .with(VelocityComponent {velocity : Vector3::new(0.0, 0.0, 0.0)})
-            .with(BoxCollider2DComponent {position: Vector2::new(position.x, position.y),
-                size : Vector2::new(scale.x * 2.0, scale.y * 2.0), corners})
-            .build();
-
-        let cam_position = Vector3::new(0.0, 0.0, -1.0);
-        let cam_dimensions = Vector2::new(window.data.width as f32, window.data.height as f32);
-
-        let camera = GameState::create_entity(state)
-            .with(PositionComponent {position : cam_position})
-            .with(OrthographicCameraComponent
-                {   dimensions: cam_dimensions,
-                    view: Matrix4::new_translation(&cam_position),
-                    projection : Matrix4::new_orthographic(
-                        -(cam_dimensions.x / 2.0),
-                        cam_dimensions.x / 2.0,
-                        -(cam_dimensions.y / 2.0),
-                        cam_dimensions.y / 2.0, 1.0, -1.0 )})
-            .build();
-
-        Ok(camera)
+    /// Returns an iterator over every entity which matches the query, e.g:
+    /// state.query::<(&PositionComponent, &mut VelocityComponent, Option<&ScaleComponent>)>()
+    /// Panics if the query asks for mutable access to a component more than once.
+    pub fn query<'a, Q : Fetch<'a>>(&'a mut self) -> Query<'a, Q> {
+
+        Query::new(self)
     }
+
+    /// Runs a query against a single entity. Returns None if the entity does not match.
+    pub fn query_one<'a, Q : Fetch<'a>>(&'a mut self, index : &Entity) -> Option<Q::Item> {
+
+        validate_access::<Q>();
+
+        if !self.is_alive(index) {
+            return None
+        }
+
+        let location = self.tables.location(index);
+
+        unsafe {
+            let fetch = Q::init(self);
+            Q::fetch(&fetch, index, location)
+        }
+    }
+
+    /// Spawns every entity in a scene file into the world. Returns the spawned entities.
+    pub fn load_scene(&mut self, path : &str) -> Result<Vec<Entity>, Error> {
+
+        Scene::load(path)?.spawn(self)
+    }
+
+    /// Writes every entity in the world to a scene file.
+    pub fn save_scene(&self, path : &str) -> Result<(), Error> {
+
+        Scene::from_state(self).save(path)
+    }
+
+    /// Lets components of type T be captured in snapshots.
+    pub fn register_snapshot<T : Snapshot>(&mut self) {
+
+        self.registry.register_snapshot::<T>();
+    }
+
+    /// Copies the whole world into a snapshot which can be restored later.
+    /// SEE: snapshot
+    pub fn snapshot(&self) -> Result<WorldSnapshot, Error> {
+
+        WorldSnapshot::take(self)
+    }
+
+    /// Puts the world back the way it was when the snapshot was taken.
+    pub fn restore(&mut self, snapshot : &WorldSnapshot) -> Result<(), Error> {
+
+        snapshot.restore(self)
+    }
+
+    /// Makes components of type T reachable through reflection by their short name.
+    pub fn register_reflect<T : Component + Reflect>(&mut self) {
+
+        self.registry.register_reflect::<T>();
+    }
+
+    /// Lets components of type T be saved in scenes and prefabs under the given name, e.g.
+    /// state.register_scene_component::<HealthComponent>("health")
+    /// SEE: scene::SceneComponent
+    pub fn register_scene_component<T : SceneComponent>(&mut self, name : &'static str) {
+
+        self.registry.register_scene_component::<T>(name);
+    }
+
+    /// Reads a component field by path, e.g. "PositionComponent.position.x".
+    /// SEE: reflect
+    pub fn reflect_get(&self, index : &Entity, path : &str) -> Result<Value, Error> {
+
+        let (info, path) = self.reflect_path(path)?;
+
+        (info.get)(self, index, &path)
+    }
+
+    /// Writes a component field by path. The component is marked as changed.
+    pub fn reflect_set(&mut self, index : &Entity, path : &str, value : Value) -> Result<(), Error> {
+
+        let (info, path) = self.reflect_path(path)?;
+
+        (info.set)(self, index, &path, value)
+    }
+
+    /// The fields of one of the entity's components, looked up by the component's short name.
+    pub fn reflect_fields(&self, index : &Entity, component : &str) -> Result<Vec<FieldInfo>, Error> {
+
+        let info = self.registry.reflect_info(component)
+            .ok_or_else(|| err_msg(format!("{} is not a reflected type.", component)))?;
+
+        (info.fields)(self, index).ok_or_else(|| err_msg(format!("Entity {:?} has no {}", index, component)))
+    }
+
+    /// The short names of every reflected component the entity has.
+    pub fn reflected_components(&self, index : &Entity) -> Vec<&'static str> {
+
+        self.registry.reflected_names()
+            .into_iter()
+            .filter(|name| self.registry.reflect_info(name).is_some_and(|info| (info.has)(self, index)))
+            .collect()
+    }
+
+    /// Splits a path into the reflected component it starts with and the field path after it.
+    fn reflect_path<'a>(&self, path : &'a str) -> Result<(ReflectInfo, Vec<&'a str>), Error> {
+
+        let mut segments = path.split('.');
+
+        let component = segments.next().unwrap_or("");
+
+        let info = self.registry.reflect_info(component)
+            .ok_or_else(|| err_msg(format!("{} is not a reflected type.", component)))?;
+
+        Ok((*info, segments.collect()))
+    }
+
+    /// Adds an edit to the History resource, if there is one.
+    /// SEE: history
+    pub fn record_edit(&mut self, edit : Edit) {
+
+        if let Some(history) = self.try_resource_mut::<History>() {
+            history.record(edit);
+        }
+    }
+
+    /// Undoes the latest step in the History resource. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> Result<bool, Error> {
+
+        history::undo(self)
+    }
+
+    /// Redoes the latest undone step. Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> Result<bool, Error> {
+
+        history::redo(self)
+    }
+
+    /// Spawns a prefab from the Prefabs resource with some of its fields overridden, e.g:
+    /// state.spawn_prefab("crate", json!({ "position": [100.0, 0.0, 0.0] }))
+    pub fn spawn_prefab(&mut self, name : &str, overrides : Value) -> Result<Entity, Error> {
+
+        let entity = match self.try_resource::<Prefabs>() {
+            Some(prefabs) => prefabs.build(name, &overrides)?,
+            None => return Err(err_msg("No prefabs have been loaded."))
+        };
+
+        entity.spawn(self)
+    }
+}
+
+fn swap_event_buffers<T : 'static>(state : &mut GameState) {
+
+    state.resource_mut::<Events<T>>().update();
 }
 
 /// Struct for the EntityBuilder. The struct allows the user to easily build and configure entities
@@ -237,7 +773,7 @@ pub struct EntityBuilder<'a>{
 impl<'a> EntityBuilder<'a> {
 
     /// Basic Constructor.
-    pub fn new(id : GenerationalIndex, state : &'a mut GameState) -> EntityBuilder {
+    pub fn new(id : GenerationalIndex, state : &'a mut GameState) -> EntityBuilder<'a> {
 
         EntityBuilder { id, state}
     }
@@ -1,237 +1,756 @@
-use crate::generational_index::generational_index::EntryValue::Empty;
+use std::convert::TryFrom;
+use std::fmt;
 
-#[derive(Clone, Copy)]
+/// An entity id - a 32 bit slot index plus the 32 bit generation of the slot it was handed out for.
+/// The pair packs into a single u64 (see to_bits).
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct GenerationalIndex {
-    pub index : usize,
-    pub generation: u64
+    pub index : u32,
+    pub generation : u32
 }
 
 impl GenerationalIndex {
 
+    pub fn new(index : u32, generation : u32) -> GenerationalIndex {
+        GenerationalIndex { index, generation }
+    }
+
     pub fn index(&self) -> usize {
-        self.index
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Packs the id into a u64, generation in the high half.
+    pub fn to_bits(&self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    pub fn from_bits(bits : u64) -> GenerationalIndex {
+        GenerationalIndex::new(bits as u32, (bits >> 32) as u32)
     }
 }
 
-pub enum EntryValue {
-    // Generation   // PackedIndex  //EntityIndex
-    Full((u64,      usize,          usize)),
-    Empty
+/// Printed as index v generation, e.g. 3v0.
+impl fmt::Debug for GenerationalIndex {
+
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
 }
 
-// TODO: FIND WAY TO SORT COMPONENTS INSTEAD OF STORING GENERATIONAL INDEX FOR EACH ONE.
+/// Records when a value was added to an array and when it was last changed, measured in the world's
+/// change ticks. Used for change detection.
 #[derive(Clone, Copy)]
-pub struct ArrayEntry<T> {
-    pub value : T,
-    pub owned_entity: GenerationalIndex
+pub struct ComponentTicks {
+    pub added : u64,
+    pub changed : u64
 }
 
-pub struct GenerationalIndexArray<T> {
+impl ComponentTicks {
 
-    pub unpacked_entries : Vec<EntryValue>,
-    pub entries : Vec<Option<ArrayEntry<T>>>
-}
+    pub fn new(tick : u64) -> ComponentTicks {
+        ComponentTicks { added : tick, changed : tick }
+    }
 
-impl<T> GenerationalIndexArray<T> {
+    pub fn is_added(&self, since : u64) -> bool {
+        self.added > since
+    }
 
-    pub fn new() -> GenerationalIndexArray<T> {
+    pub fn is_changed(&self, since : u64) -> bool {
+        self.changed > since
+    }
+}
 
-        let array = GenerationalIndexArray {
-            unpacked_entries: Vec::with_capacity(1024),
-            entries: Vec::with_capacity(1024)
-        };
+/// A sparse set keyed by generational index.
+/// sparse - indexed by entity index. Stores the generation the value belongs to and its dense slot.
+/// dense - tightly packed component values. Iteration only ever touches this vector.
+/// entities - the owning entity of each dense value (same order as dense).
+/// ticks - the added/changed ticks of each dense value (same order as dense).
+/// removed - every index removed recently, along with the tick it was removed on.
+/// Removal swaps the last value into the removed slot and fixes up that entity's sparse entry, so
+/// it is O(1) and never leaves holes.
+pub struct GenerationalIndexArray<T> {
 
-        array
-    }
+    sparse : Vec<Option<(u32, usize)>>,
+    dense : Vec<T>,
+    entities : Vec<GenerationalIndex>,
+    ticks : Vec<ComponentTicks>,
+    removed : Vec<(GenerationalIndex, u64)>
+}
 
-    pub fn set_empty(&mut self) {
+impl<T> Default for GenerationalIndexArray<T> {
 
-        //println!("Setting empty...");
-        &mut self.unpacked_entries.push(EntryValue::Empty);
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    pub fn set(&mut self, index : &GenerationalIndex, value : T) {
+impl<T> GenerationalIndexArray<T> {
 
-        let saved_index : (u64, usize, usize);
+    pub fn new() -> GenerationalIndexArray<T> {
 
-        if let Some(idx) = self.get_unpacked_index(index) {
+        GenerationalIndexArray {
+            sparse: Vec::with_capacity(1024),
+            dense: Vec::with_capacity(1024),
+            entities: Vec::with_capacity(1024),
+            ticks: Vec::with_capacity(1024),
+            removed: Vec::new()
+        }
+    }
 
-            self.entries[idx.1] = Some(ArrayEntry {value, owned_entity: index.clone()} );
-            saved_index = idx;
+    /// Inserts a value for the index, replacing any value it already had. A value left behind by an
+    /// older generation of the same index is overwritten, but a stale index never overwrites the
+    /// value of a newer generation - the value is dropped and false is returned. Replacing a value
+    /// counts as a change, anything else counts as an addition.
+    pub fn set(&mut self, index : &GenerationalIndex, value : T, tick : u64) -> bool {
 
-        } else {
-            //println!("Pushing back value at index: {}", index.index);
-            self.entries.push(Some(ArrayEntry {value, owned_entity: index.clone()}));
-            //println!("length: {}", self.entries.len());
-            saved_index = ( index.generation, self.entries.len() - 1, index.index.clone());
+        if index.index() >= self.sparse.len() {
+            self.sparse.resize_with(index.index() + 1, || None);
         }
 
-        if index.index < self.unpacked_entries.len() {
-            //println!("Adding to unpacked...");
-            self.unpacked_entries[index.index] = EntryValue::Full((index.generation.clone(), saved_index.1, index.index.clone()))
+        match self.sparse[index.index()] {
+            Some((generation, _)) if generation > index.generation => return false,
+            Some((generation, dense_index)) => {
+                self.dense[dense_index] = value;
+                self.entities[dense_index] = *index;
+                self.sparse[index.index()] = Some((index.generation, dense_index));
+
+                if generation == index.generation {
+                    self.ticks[dense_index].changed = tick;
+                } else {
+                    self.ticks[dense_index] = ComponentTicks::new(tick);
+                }
+            },
+            None => {
+                self.dense.push(value);
+                self.entities.push(*index);
+                self.ticks.push(ComponentTicks::new(tick));
+                self.sparse[index.index()] = Some((index.generation, self.dense.len() - 1));
+            }
         }
-    }
 
-    pub fn get_unpacked_index(&self, index : &GenerationalIndex) -> Option<(u64, usize, usize)>{
+        true
+    }
 
-        let mut success = None;
+    /// Returns the dense slot of the index's value, provided the generations match.
+    pub fn dense_index(&self, index : &GenerationalIndex) -> Option<usize> {
 
-        if index.index() < self.unpacked_entries.len() {
-            match self.unpacked_entries[index.index()] {
-                EntryValue::Full(v) => { success = Some(v) },
-                EntryValue::Empty => ()
-            }
+        match self.sparse.get(index.index()) {
+            Some(Some((generation, dense_index))) if *generation == index.generation => Some(*dense_index),
+            _ => None
         }
-        success
     }
 
     pub fn get(&self, index : &GenerationalIndex) -> Option<&T> {
 
-        let mut value : Option<&T> = None;
+        let dense_index = self.dense_index(index)?;
 
-        if self.contains(index) {
+        Some(&self.dense[dense_index])
+    }
 
-            //println!("fetching for index: {} generation: {}", index.index, index.generation);
+    pub fn get_mut(&mut self, index : &GenerationalIndex) -> Option<&mut T> {
 
-            //println!("{}", index.index);
+        let dense_index = self.dense_index(index)?;
 
-            let entry = self.entries[self.get_unpacked_index(index).unwrap().1].as_ref().unwrap();
+        Some(&mut self.dense[dense_index])
+    }
 
-                if index.generation == entry.owned_entity.generation {
-                    value = Some(&entry.value);
-                }
-            }
-        value
+    /// Returns the value along with its ticks, so the caller can record a change.
+    pub fn get_mut_with_ticks(&mut self, index : &GenerationalIndex) -> Option<(&mut T, &mut ComponentTicks)> {
+
+        let dense_index = self.dense_index(index)?;
+
+        Some((&mut self.dense[dense_index], &mut self.ticks[dense_index]))
     }
 
-    pub fn contains(&self, index : &GenerationalIndex) -> bool {
+    pub fn ticks(&self, index : &GenerationalIndex) -> Option<&ComponentTicks> {
 
-        let mut success = false;
+        let dense_index = self.dense_index(index)?;
 
-        if index.index() < self.unpacked_entries.len() {
-            match &self.unpacked_entries[index.index()] {
-                EntryValue::Full(gen) => if gen.0 == index.generation { success = true },
-                EntryValue::Empty => success = false
-            }
-        }
-        success
+        Some(&self.ticks[dense_index])
+    }
+
+    pub fn contains(&self, index : &GenerationalIndex) -> bool {
+
+        self.dense_index(index).is_some()
     }
 
-    pub fn remove(&mut self, index : &GenerationalIndex) {
+    /// Removes and returns the index's value. The last value is swapped into the freed slot.
+    /// The removal is recorded so that it can be picked up by removed_since.
+    pub fn remove(&mut self, index : &GenerationalIndex, tick : u64) -> Option<T> {
+
+        let dense_index = self.dense_index(index)?;
+
+        self.sparse[index.index()] = None;
+
+        let value = self.dense.swap_remove(dense_index);
+        self.entities.swap_remove(dense_index);
+        self.ticks.swap_remove(dense_index);
+
+        self.removed.push((*index, tick));
+
+        // Point the entity which was moved into the freed slot at its new location.
+        if dense_index < self.entities.len() {
+
+            let moved = self.entities[dense_index];
 
-        if self.contains(index) {
-            let unpacked_index = self.get_unpacked_index(index).unwrap().1;
-            self.entries.remove(unpacked_index);
-            self.unpacked_entries[index.index()] = Empty;
-            self.update_entries();
+            self.sparse[moved.index()] = Some((moved.generation, dense_index));
         }
+
+        Some(value)
     }
 
-    pub fn update_entries(&mut self) {
+    /// Every index which was removed after the given tick.
+    pub fn removed_since(&self, since : u64) -> impl Iterator<Item = GenerationalIndex> + '_ {
 
-        let mut idx : usize = 0;
+        self.removed.iter().filter(move |(_, tick)| *tick > since).map(|(index, _)| *index)
+    }
 
-        let mut coordinates : Vec<(GenerationalIndex, usize)> = Vec::new();
+    /// Forgets every removal recorded on or before the given tick.
+    pub fn prune_removed(&mut self, before : u64) {
 
-        self.entries.iter().for_each(|entry| {
+        self.removed.retain(|(_, tick)| *tick > before);
+    }
 
-            coordinates.push((entry.as_ref().unwrap().owned_entity.clone(), idx));
-            idx+= 1;
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
 
-        });
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
 
-        coordinates.iter().for_each(|coordinate| {
-           let entry = &self.unpacked_entries[coordinate.0.index];
-              match entry {
-                  EntryValue::Full(mut coord) => {
-                      coord.1 = coordinate.1;
-                  },
-                  EntryValue::Empty => ()
-           };
-        });
+    /// The owning entity of every stored value, in dense order.
+    pub fn entities(&self) -> &[GenerationalIndex] {
+        &self.entities
     }
 
-    pub fn get_mut(&mut self, index : &GenerationalIndex) -> Option<&mut T> {
+    /// Every stored value, in dense order.
+    pub fn values(&self) -> &[T] {
+        &self.dense
+    }
 
-        let mut value = None;
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.dense
+    }
 
-        if self.contains(index) {
+    /// Pointers to the first value and its ticks, so a query can hand out values from several dense
+    /// slots at once without borrowing the whole array for each one. Only valid until the array is
+    /// next changed.
+    pub fn as_mut_ptrs(&mut self) -> (*mut T, *mut ComponentTicks) {
+        (self.dense.as_mut_ptr(), self.ticks.as_mut_ptr())
+    }
 
-            let idx = self.get_unpacked_index(index).as_ref().unwrap().1;
-            let entry = &mut self.entries[idx];
+    pub fn iter(&self) -> impl Iterator<Item = (GenerationalIndex, &T)> {
+        self.entities.iter().cloned().zip(self.dense.iter())
+    }
 
-                if let Some(val) = entry.as_mut() {
-                    if index.generation == val.owned_entity.generation {
-                        value = Some(&mut val.value);
-                    }
-                }
-            }
-        value
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (GenerationalIndex, &mut T)> {
+        self.entities.iter().cloned().zip(self.dense.iter_mut())
+    }
+
+    /// Copies the array using the given function to copy each value, for types which can't simply be
+    /// cloned. Every copied value counts as added on the given tick, and no removals are carried over.
+    /// Stops at the first value which fails to copy.
+    pub fn copy_with<F, E>(&self, copy : F, tick : u64) -> Result<GenerationalIndexArray<T>, E> where F : FnMut(&T) -> Result<T, E> {
+
+        Ok(GenerationalIndexArray {
+            sparse : self.sparse.clone(),
+            dense : self.dense.iter().map(copy).collect::<Result<Vec<T>, E>>()?,
+            entities : self.entities.clone(),
+            ticks : vec![ComponentTicks::new(tick); self.ticks.len()],
+            removed : Vec::new()
+        })
     }
 }
 
 /// Used to create the generational indices which will be stored in the generational index array.
-
+/// A slot's generation goes up every time it is reused, so an index handed out before the slot was
+/// freed never matches it again. A slot whose generation has reached u32::MAX is retired rather than
+/// reused, so generations never wrap around.
+#[derive(Clone)]
 pub struct AllocatorEntry {
     pub live : bool,
-    generation : u64
+    generation : u32
 }
 
+#[derive(Clone)]
 pub struct GenerationalIndexAllocator {
     entries : Vec<AllocatorEntry>,
-    free : Vec<usize>
+    free : Vec<u32>,
+    live : usize
+}
+
+impl Default for GenerationalIndexAllocator {
+
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GenerationalIndexAllocator {
 
     pub fn new() -> GenerationalIndexAllocator {
 
-        let allocator = GenerationalIndexAllocator {
+        GenerationalIndexAllocator {
             entries: Vec::with_capacity(1024),
-            free : Vec::with_capacity(1024)
-        };
-
-        allocator
+            free : Vec::with_capacity(1024),
+            live : 0
+        }
     }
 
+    /// Hands out a free slot with its generation bumped, or a new slot if none are free. Panics if
+    /// every one of the 2^32 slots is live or retired.
     pub fn allocate(&mut self) -> GenerationalIndex {
 
-        let index : usize;
-        let mut generation : u64 = 0;
+        self.live += 1;
 
-        if !self.free.is_empty() {
+        match self.free.pop() {
+            Some(index) => {
 
-            let free_index = self.free[0];
+                let entry = &mut self.entries[index as usize];
 
-            let mut entry = &mut self.entries[free_index];
+                entry.generation += 1;
+                entry.live = true;
 
-            entry.generation += 1;
-            entry.live = true;
+                GenerationalIndex::new(index, entry.generation)
+            },
+            None => {
 
-            index = self.free.pop().unwrap();
-            generation = entry.generation;
+                let index = next_slot(self.entries.len());
 
-        } else {
+                self.entries.push(AllocatorEntry { live : true, generation : 0 });
 
-            self.entries.push(AllocatorEntry { live : true, generation : 0});
-            index = self.entries.len() - 1;
+                GenerationalIndex::new(index, 0)
+            }
         }
-
-        //println!("Generated index with index: {}, and generation: {}", index, generation);
-        GenerationalIndex {index, generation }
     }
 
+    /// Frees the index's slot. Returns false, leaving everything untouched, if the index is stale or
+    /// has already been freed.
     pub fn deallocate(&mut self, index : &GenerationalIndex) -> bool {
 
-        self.free.push(index.index());
-        self.entries[index.index].live = false;
+        if !self.is_live(index) {
+            return false
+        }
+
+        let entry = &mut self.entries[index.index()];
+
+        entry.live = false;
+
+        if entry.generation < u32::MAX {
+            self.free.push(index.index);
+        }
+
+        self.live -= 1;
 
         true
     }
 
+    /// Returns true if the index's slot is in use by that same generation.
     pub fn is_live(&self, index : &GenerationalIndex) -> bool {
 
-        //println!("Index is live: {}", self.entries[index.index].live);
-        self.entries[index.index].live
+        match self.entries.get(index.index()) {
+            Some(entry) => entry.live && entry.generation == index.generation,
+            None => false
+        }
+    }
+
+    /// The number of live indices.
+    pub fn len(&self) -> usize {
+        self.live
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live == 0
+    }
+
+    /// Every live index, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = GenerationalIndex> + '_ {
+
+        self.entries.iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.live)
+            .map(|(index, entry)| GenerationalIndex::new(index as u32, entry.generation))
     }
-}
\ No newline at end of file
+}
+
+/// The index of the slot after the given number of slots. Panics once indices no longer fit in 32
+/// bits, rather than wrapping around onto slots which are still in use.
+fn next_slot(slots : usize) -> u32 {
+
+    match u32::try_from(slots) {
+        Ok(index) => index,
+        Err(_) => panic!("Out of entity indices: all {} slots are live or retired.", slots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ecs::{PositionComponent, VelocityComponent, ScaleComponent};
+    use crate::game_state::GameState;
+    use nalgebra::Vector3;
+
+    fn index(index : u32, generation : u32) -> GenerationalIndex {
+        GenerationalIndex::new(index, generation)
+    }
+
+    #[test]
+    fn set_then_get() {
+
+        let mut array = GenerationalIndexArray::new();
+
+        array.set(&index(3, 0), "three", 1);
+        array.set(&index(0, 0), "zero", 1);
+
+        assert_eq!(array.get(&index(3, 0)), Some(&"three"));
+        assert_eq!(array.get(&index(0, 0)), Some(&"zero"));
+        assert_eq!(array.get(&index(1, 0)), None);
+        assert_eq!(array.get(&index(7, 0)), None);
+        assert_eq!(array.len(), 2);
+    }
+
+    #[test]
+    fn set_replaces_and_counts_as_a_change() {
+
+        let mut array = GenerationalIndexArray::new();
+
+        array.set(&index(2, 0), 1, 1);
+        array.set(&index(2, 0), 2, 5);
+
+        assert_eq!(array.get(&index(2, 0)), Some(&2));
+        assert_eq!(array.len(), 1);
+
+        let ticks = array.ticks(&index(2, 0)).unwrap();
+
+        assert_eq!((ticks.added, ticks.changed), (1, 5));
+    }
+
+    #[test]
+    fn remove_returns_the_value_and_records_it() {
+
+        let mut array = GenerationalIndexArray::new();
+
+        array.set(&index(1, 0), 10, 1);
+
+        assert_eq!(array.remove(&index(1, 0), 4), Some(10));
+        assert_eq!(array.get(&index(1, 0)), None);
+        assert_eq!(array.remove(&index(1, 0), 4), None);
+        assert!(array.is_empty());
+
+        assert_eq!(array.removed_since(3).collect::<Vec<_>>(), vec![index(1, 0)]);
+        assert_eq!(array.removed_since(4).count(), 0);
+    }
+
+    #[test]
+    fn reinsert_after_remove() {
+
+        let mut array = GenerationalIndexArray::new();
+
+        array.set(&index(1, 0), 10, 1);
+        array.remove(&index(1, 0), 2);
+        array.set(&index(1, 1), 11, 3);
+
+        assert_eq!(array.get(&index(1, 1)), Some(&11));
+        assert_eq!(array.get(&index(1, 0)), None);
+        assert_eq!(array.len(), 1);
+
+        let ticks = array.ticks(&index(1, 1)).unwrap();
+
+        assert_eq!((ticks.added, ticks.changed), (3, 3));
+    }
+
+    #[test]
+    fn generation_mismatch_misses() {
+
+        let mut array = GenerationalIndexArray::new();
+
+        array.set(&index(4, 2), "current", 1);
+
+        assert_eq!(array.get(&index(4, 1)), None);
+        assert_eq!(array.get(&index(4, 3)), None);
+        assert_eq!(array.get_mut(&index(4, 1)), None);
+        assert!(!array.contains(&index(4, 1)));
+        assert_eq!(array.remove(&index(4, 1), 2), None);
+        assert_eq!(array.get(&index(4, 2)), Some(&"current"));
+    }
+
+    #[test]
+    fn set_over_an_older_generation_counts_as_an_addition() {
+
+        let mut array = GenerationalIndexArray::new();
+
+        array.set(&index(4, 0), "old", 1);
+        array.set(&index(4, 1), "new", 6);
+
+        assert_eq!(array.get(&index(4, 0)), None);
+        assert_eq!(array.get(&index(4, 1)), Some(&"new"));
+        assert_eq!(array.entities(), &[index(4, 1)]);
+
+        let ticks = array.ticks(&index(4, 1)).unwrap();
+
+        assert_eq!((ticks.added, ticks.changed), (6, 6));
+    }
+
+    #[test]
+    fn swap_remove_fixes_up_the_moved_index() {
+
+        let mut array = GenerationalIndexArray::new();
+
+        for slot in 0..4 {
+            array.set(&index(slot, 0), slot * 10, 1);
+        }
+
+        // The last value (slot 3) is swapped into the freed dense slot.
+        array.remove(&index(1, 0), 2);
+
+        assert_eq!(array.entities(), &[index(0, 0), index(3, 0), index(2, 0)]);
+        assert_eq!(array.values(), &[0, 30, 20]);
+        assert_eq!(array.dense_index(&index(3, 0)), Some(1));
+        assert_eq!(array.get(&index(3, 0)), Some(&30));
+
+        *array.get_mut(&index(3, 0)).unwrap() = 31;
+
+        assert_eq!(array.get(&index(3, 0)), Some(&31));
+
+        // Removing the last value has nothing to swap in.
+        array.remove(&index(2, 0), 3);
+
+        assert_eq!(array.entities(), &[index(0, 0), index(3, 0)]);
+        assert_eq!(array.get(&index(0, 0)), Some(&0));
+        assert_eq!(array.get(&index(3, 0)), Some(&31));
+    }
+
+    /// Tiny xorshift generator, so the property tests below are repeatable without extra crates.
+    struct Rng(u64);
+
+    impl Rng {
+
+        fn next(&mut self) -> u64 {
+
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound : usize) -> usize {
+            (self.next() % bound as u64) as usize
+        }
+    }
+
+    /// Runs random allocations and frees against an allocator and an array, checking after every step
+    /// that live handles resolve to their own value and that no freed handle ever resolves again, even
+    /// once its slot has been handed out again.
+    fn check_stale_handles(seed : u64, steps : usize) {
+
+        let mut rng = Rng(seed);
+        let mut allocator = GenerationalIndexAllocator::new();
+        let mut array = GenerationalIndexArray::new();
+        let mut live : Vec<(GenerationalIndex, usize)> = Vec::new();
+        let mut freed : Vec<GenerationalIndex> = Vec::new();
+
+        for step in 0..steps {
+
+            match rng.below(3) {
+                // Allocate twice as often as freeing, so slots are reused at every size.
+                0 | 1 if live.len() < 64 => {
+
+                    let handle = allocator.allocate();
+
+                    assert!(live.iter().all(|(other, _)| other.index != handle.index), "{:?} handed out twice", handle);
+                    assert!(!freed.contains(&handle), "{:?} handed out again after being freed", handle);
+
+                    array.set(&handle, step, step as u64);
+                    live.push((handle, step));
+                },
+                _ if !live.is_empty() => {
+
+                    let (handle, value) = live.swap_remove(rng.below(live.len()));
+
+                    assert!(allocator.deallocate(&handle));
+                    assert_eq!(array.remove(&handle, step as u64), Some(value));
+
+                    freed.push(handle);
+                },
+                _ => {}
+            }
+
+            // Freeing a stale handle again must change nothing.
+            if !freed.is_empty() {
+
+                let stale = freed[rng.below(freed.len())];
+
+                assert!(!allocator.deallocate(&stale));
+                assert_eq!(array.remove(&stale, step as u64), None);
+            }
+
+            // Writing through a stale handle must never replace the value of the slot's new owner.
+            for stale in freed.iter() {
+                if live.iter().any(|(handle, _)| handle.index == stale.index) {
+                    assert!(!array.set(stale, usize::MAX, step as u64), "stale {:?} was written after step {}", stale, step);
+                }
+            }
+
+            for stale in freed.iter() {
+                assert!(!allocator.is_live(stale), "stale {:?} is live after step {}", stale, step);
+                assert_eq!(array.get(stale), None, "stale {:?} resolved after step {}", stale, step);
+            }
+
+            for (handle, value) in live.iter() {
+                assert!(allocator.is_live(handle));
+                assert_eq!(array.get(handle), Some(value));
+            }
+
+            assert_eq!(allocator.len(), live.len());
+            assert_eq!(array.len(), live.len());
+        }
+    }
+
+    #[test]
+    fn stale_handles_never_resolve() {
+
+        for seed in 1..=64u64 {
+            check_stale_handles(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15), 400);
+        }
+    }
+
+    /// Spawns and despawns entities at random, adding components through every stale handle after
+    /// each step, both to sparse and table storage. Every write must be refused, live entities must
+    /// keep their own values, and queries must only ever see live entities.
+    fn check_stale_writes(seed : u64, steps : usize) {
+
+        let mut rng = Rng(seed);
+        let mut state = GameState::create_initial_state();
+        let mut live : Vec<(GenerationalIndex, f32)> = Vec::new();
+        let mut freed : Vec<GenerationalIndex> = Vec::new();
+
+        for step in 0..steps {
+
+            let value = step as f32;
+
+            match rng.below(3) {
+                0 | 1 if live.len() < 32 => {
+
+                    let entity = GameState::create_entity(&mut state)
+                        .with(PositionComponent { position : Vector3::new(value, 0.0, 0.0) })
+                        .with(VelocityComponent { velocity : Vector3::new(value, 0.0, 0.0) })
+                        .build();
+
+                    live.push((entity, value));
+                },
+                _ if !live.is_empty() => {
+
+                    let (entity, _) = live.swap_remove(rng.below(live.len()));
+
+                    assert!(state.despawn(&entity));
+
+                    freed.push(entity);
+                },
+                _ => {}
+            }
+
+            for stale in freed.iter() {
+
+                let stray = Vector3::new(-1.0, -1.0, -1.0);
+
+                assert!(!state.add_component_to(PositionComponent { position : stray }, stale), "stale {:?} was written after step {}", stale, step);
+                assert!(!state.add_component_to(VelocityComponent { velocity : stray }, stale));
+                assert!(!state.add_component_to(ScaleComponent { scale : stray }, stale));
+            }
+
+            for (entity, value) in live.iter() {
+                assert_eq!(state.get::<PositionComponent>(entity).unwrap().position.x, *value);
+                assert_eq!(state.get::<VelocityComponent>(entity).unwrap().velocity.x, *value);
+                assert!(state.get::<ScaleComponent>(entity).is_none());
+            }
+
+            let found = state.query::<(GenerationalIndex, &PositionComponent, &VelocityComponent)>()
+                .map(|(entity, _, _)| entity)
+                .collect::<Vec<GenerationalIndex>>();
+
+            assert_eq!(found.len(), live.len());
+            assert!(found.iter().all(|entity| state.is_alive(entity)), "a query found a dead entity after step {}", step);
+            assert_eq!(state.query::<&ScaleComponent>().count(), 0);
+        }
+    }
+
+    #[test]
+    fn stale_handles_never_write() {
+
+        for seed in 1..=16u64 {
+            check_stale_writes(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15), 200);
+        }
+    }
+
+    #[test]
+    fn queries_skip_components_left_on_dead_entities() {
+
+        let mut state = GameState::create_initial_state();
+
+        let entity = GameState::create_entity(&mut state).build();
+
+        state.despawn(&entity);
+
+        // Written straight into storage, past the liveness check in register_component.
+        state.get_map_mut::<VelocityComponent>().set(&entity, VelocityComponent { velocity : Vector3::new(1.0, 0.0, 0.0) }, 1);
+        state.tables.insert(&entity, PositionComponent { position : Vector3::new(1.0, 0.0, 0.0) }, 1);
+
+        assert_eq!(state.query::<&VelocityComponent>().count(), 0);
+        assert_eq!(state.query::<&PositionComponent>().count(), 0);
+        assert_eq!(state.query::<(GenerationalIndex, Option<&VelocityComponent>)>().count(), 0);
+    }
+
+    #[test]
+    fn freed_slots_are_reused_with_a_new_generation() {
+
+        let mut allocator = GenerationalIndexAllocator::new();
+
+        let first = allocator.allocate();
+
+        allocator.deallocate(&first);
+
+        let second = allocator.allocate();
+
+        assert_eq!(second.index, first.index);
+        assert_eq!(second.generation, first.generation + 1);
+        assert!(!allocator.is_live(&first));
+        assert!(allocator.is_live(&second));
+    }
+
+    #[test]
+    fn slots_at_the_last_generation_are_retired() {
+
+        let mut allocator = GenerationalIndexAllocator::new();
+
+        allocator.entries.push(AllocatorEntry { live : false, generation : u32::MAX - 1 });
+        allocator.free.push(0);
+
+        let last = allocator.allocate();
+
+        assert_eq!(last, index(0, u32::MAX));
+        assert!(allocator.deallocate(&last));
+
+        // The slot can't be handed out again, so a new one is used instead.
+        assert_eq!(allocator.allocate(), index(1, 0));
+        assert!(!allocator.is_live(&last));
+    }
+
+    #[test]
+    fn the_last_slot_index_is_usable() {
+
+        assert_eq!(next_slot(u32::MAX as usize), u32::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "Out of entity indices")]
+    fn running_out_of_slot_indices_panics() {
+
+        next_slot(u32::MAX as usize + 1);
+    }
+}
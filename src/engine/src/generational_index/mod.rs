@@ -1 +1,2 @@
-pub mod generational_index;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod generational_index;
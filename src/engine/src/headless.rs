@@ -0,0 +1,86 @@
+//! HEADLESS
+//! Runs the engine without a window or GL context, so simulations can run on CI servers and in
+//! cargo test on machines with no display or GPU. Runners are built with App::headless, so the
+//! state is set up exactly as App::run sets it up and stepped through the same schedule.
+//!
+//! Nothing loads GL, so GL-backed components are built with empty handles and the render system's
+//! run condition keeps it from running. Each step advances Time by one fixed step rather than
+//! measuring the clock, so a run is repeatable however fast the machine is. Nothing feeds input
+//! either - the input resources stay idle unless the caller sets them.
+
+use crate::ecs::schedule::Schedule;
+use crate::game_state::GameState;
+use crate::input::recording::{InputRecording, FrameInput};
+use crate::profiler::Profiler;
+use crate::time::Time;
+use failure::Error;
+
+pub struct HeadlessRunner {
+
+    pub state : GameState,
+    schedule : Schedule
+}
+
+impl HeadlessRunner {
+
+    /// Runs the schedule's startup systems against a state the caller has set up.
+    pub fn with_state(mut schedule : Schedule, mut state : GameState) -> Result<HeadlessRunner, Error> {
+
+        schedule.run_startup(&mut state)?;
+
+        Ok(HeadlessRunner { state, schedule })
+    }
+
+    /// Runs a single frame, one fixed step long.
+    pub fn step(&mut self) -> Result<(), Error> {
+
+        if let Some(time) = self.state.try_resource_mut::<Time>() {
+            let seconds = time.fixed_delta;
+            time.advance(seconds);
+        }
+
+        self.run_frame()
+    }
+
+    /// Runs a single recorded frame, with its length and input.
+    pub fn step_with(&mut self, input : &FrameInput) -> Result<(), Error> {
+
+        input.replay(&mut self.state);
+
+        self.run_frame()
+    }
+
+    /// Runs every frame of a recording, then checks the world matches the one it was recorded with.
+    pub fn replay(&mut self, recording : &InputRecording) -> Result<(), Error> {
+
+        for frame in recording.frames.iter() {
+            self.step_with(frame)?;
+        }
+
+        recording.check(&self.state)
+    }
+
+    pub fn run_frames(&mut self, frames : u64) -> Result<(), Error> {
+
+        for _ in 0..frames {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    fn run_frame(&mut self) -> Result<(), Error> {
+
+        if let Some(profiler) = self.state.try_resource_mut::<Profiler>() {
+            profiler.begin_frame();
+        }
+
+        self.schedule.run(&mut self.state)?;
+
+        if let Some(profiler) = self.state.try_resource_mut::<Profiler>() {
+            profiler.end_frame();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,536 @@
+//! HISTORY
+//! An undo stack of reversible world edits, kept as a world resource. Editor-style systems record
+//! each change they make (GameState::record_edit) and GameState::undo / GameState::redo step
+//! backwards and forwards through them.
+//!
+//! Each undo step holds one or more edits. A group (begin_group ... end_group) covers a set of
+//! entities, e.g. the ones being dragged: while it is open, every set on those entities is added to
+//! the group's step, and repeated sets of the same component on the same entity are merged, so a
+//! whole drag is undone in one go. Any other edit made meanwhile still gets a step of its own.
+//!
+//! Undoing a despawn (or redoing a spawn) brings the entity back with a new id. Every edit in the
+//! history is updated to point at the new id, including entity ids held by recorded component values
+//! (see Snapshot::remap). Edits aimed at an entity which is no longer alive are skipped.
+//!
+//! If an edit in a step fails, the edits of that step which were already made are reversed again
+//! and the step goes back where it came from, so it can be retried.
+
+use crate::ecs::snapshot::Snapshot;
+use crate::ecs::ParentComponent;
+use crate::game_state::{GameState, Entity};
+use crate::scene::SceneEntity;
+use failure::{Error, err_msg};
+use std::any::TypeId;
+
+pub struct History {
+
+    undo : Vec<Vec<Edit>>,
+    redo : Vec<Vec<Edit>>,
+    group : Option<Group>
+}
+
+/// The open group - which undo step it collects into and the entities it covers.
+struct Group {
+
+    step : usize,
+    entities : Vec<Entity>
+}
+
+/// A single reversible change to the world.
+pub enum Edit {
+
+    /// The entity was spawned. data is refreshed when the spawn is undone, so redoing it brings the
+    /// entity back as it was.
+    Spawn { entity : Entity, data : SceneEntity },
+
+    /// The entity was despawned. Its parent and children are reconnected when it is brought back,
+    /// provided they are still alive.
+    Despawn { entity : Entity, data : SceneEntity, parent : Option<Entity>, children : Vec<Entity> },
+
+    /// A component was added, replaced or removed.
+    Set { entity : Entity, component : TypeId, before : Box<dyn ComponentEdit>, after : Box<dyn ComponentEdit> }
+}
+
+/// A component value (or its absence) which can be put back on an entity.
+pub trait ComponentEdit : Send + Sync {
+
+    fn apply(&self, state : &mut GameState, entity : &Entity) -> Result<(), Error>;
+
+    /// Points any entity ids the value holds at an entity's new id.
+    fn remap(&mut self, old : &Entity, new : &Entity);
+}
+
+struct ComponentValue<T>(Option<T>);
+
+impl<T : Snapshot + Send + Sync> ComponentEdit for ComponentValue<T> {
+
+    fn apply(&self, state : &mut GameState, entity : &Entity) -> Result<(), Error> {
+
+        match &self.0 {
+            Some(value) => if !state.add_component_to(value.restore()?, entity) {
+                return Err(err_msg(format!("Entity {:?} no longer exists.", entity)))
+            },
+            None => state.remove_component::<T>(entity)
+        }
+
+        Ok(())
+    }
+
+    fn remap(&mut self, old : &Entity, new : &Entity) {
+
+        if let Some(value) = &mut self.0 {
+            value.remap(old, new);
+        }
+    }
+}
+
+impl Edit {
+
+    /// Records a spawn. Call once the entity has all of its components.
+    pub fn spawn(state : &GameState, entity : &Entity) -> Edit {
+
+        Edit::Spawn { entity : *entity, data : SceneEntity::capture(state, entity) }
+    }
+
+    /// Records a despawn. Call before the entity is despawned.
+    pub fn despawn(state : &GameState, entity : &Entity) -> Edit {
+
+        Edit::Despawn {
+            entity : *entity,
+            data : SceneEntity::capture(state, entity),
+            parent : state.get::<ParentComponent>(entity).map(|parent| parent.parent),
+            children : state.children_of(entity)
+        }
+    }
+
+    /// Records a component change. None stands for the entity not having the component.
+    pub fn set<T : Snapshot + Send + Sync>(entity : &Entity, before : Option<T>, after : Option<T>) -> Edit {
+
+        Edit::Set {
+            entity : *entity,
+            component : TypeId::of::<T>(),
+            before : Box::new(ComponentValue(before)),
+            after : Box::new(ComponentValue(after))
+        }
+    }
+
+    /// Reverses the edit. Returns the old and new id of any entity which was brought back.
+    fn undo(&mut self, state : &mut GameState) -> Result<Option<(Entity, Entity)>, Error> {
+
+        match self {
+            Edit::Spawn { entity, data } => {
+                remove(state, entity, data);
+                Ok(None)
+            },
+            Edit::Despawn { entity, data, parent, children } => bring_back(state, entity, data, parent, children).map(Some),
+            Edit::Set { entity, before, .. } => {
+                if state.is_alive(entity) {
+                    before.apply(state, entity)?;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Makes the edit again. Returns the old and new id of any entity which was brought back.
+    fn redo(&mut self, state : &mut GameState) -> Result<Option<(Entity, Entity)>, Error> {
+
+        match self {
+            Edit::Spawn { entity, data } => bring_back(state, entity, data, &None, &Vec::new()).map(Some),
+            Edit::Despawn { entity, data, parent, children } => {
+                *parent = state.get::<ParentComponent>(entity).map(|parent| parent.parent);
+                *children = state.children_of(entity);
+                remove(state, entity, data);
+                Ok(None)
+            },
+            Edit::Set { entity, after, .. } => {
+                if state.is_alive(entity) {
+                    after.apply(state, entity)?;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Points the edit at an entity's new id.
+    fn remap(&mut self, old : &Entity, new : &Entity) {
+
+        let swap = |entity : &mut Entity| if entity == old { *entity = *new };
+
+        match self {
+            Edit::Spawn { entity, .. } => swap(entity),
+            Edit::Despawn { entity, parent, children, .. } => {
+                swap(entity);
+                parent.iter_mut().for_each(swap);
+                children.iter_mut().for_each(swap);
+            },
+            Edit::Set { entity, before, after, .. } => {
+                swap(entity);
+                before.remap(old, new);
+                after.remap(old, new);
+            }
+        }
+    }
+
+    /// True if the edit sets a component on one of the entities.
+    fn sets_any_of(&self, entities : &[Entity]) -> bool {
+
+        match self {
+            Edit::Set { entity, .. } => entities.contains(entity),
+            _ => false
+        }
+    }
+
+    /// True if both edits set the same component on the same entity.
+    fn merges_with(&self, edit : &Edit) -> bool {
+
+        match (self, edit) {
+            (Edit::Set { entity, component, .. }, Edit::Set { entity : next_entity, component : next_component, .. })
+                => entity == next_entity && component == next_component,
+            _ => false
+        }
+    }
+
+    /// Folds a later set of the same component into this one, keeping the original before value.
+    fn merge(&mut self, edit : Edit) {
+
+        if let (Edit::Set { after, .. }, Edit::Set { after : next_after, .. }) = (self, edit) {
+            *after = next_after;
+        }
+    }
+}
+
+/// Despawns the entity, keeping a copy of it so it can be brought back.
+fn remove(state : &mut GameState, entity : &Entity, data : &mut SceneEntity) {
+
+    if state.is_alive(entity) {
+        *data = SceneEntity::capture(state, entity);
+        state.despawn(entity);
+    }
+}
+
+/// Spawns a copy of an entity, reconnecting its parent and children.
+fn bring_back(state : &mut GameState, entity : &Entity, data : &SceneEntity, parent : &Option<Entity>, children : &[Entity]) -> Result<(Entity, Entity), Error> {
+
+    let spawned = data.spawn(state)?;
+
+    if let Some(parent) = parent {
+        if state.is_alive(parent) {
+            state.set_parent(&spawned, parent)?;
+        }
+    }
+
+    for child in children.iter() {
+        if state.is_alive(child) {
+            state.set_parent(child, &spawned)?;
+        }
+    }
+
+    Ok((*entity, spawned))
+}
+
+impl Default for History {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+
+    pub fn new() -> History {
+
+        History { undo : Vec::new(), redo : Vec::new(), group : None }
+    }
+
+    /// Adds an edit which has already been made. Anything which could be redone is forgotten.
+    pub fn record(&mut self, edit : Edit) {
+
+        self.redo.clear();
+
+        if let Some(group) = &self.group {
+
+            if edit.sets_any_of(&group.entities) {
+
+                let step = &mut self.undo[group.step];
+
+                match step.iter_mut().rev().find(|existing| existing.merges_with(&edit)) {
+                    Some(existing) => existing.merge(edit),
+                    None => step.push(edit)
+                }
+
+                return
+            }
+        }
+
+        self.undo.push(vec![edit]);
+    }
+
+    /// Starts collecting sets on the given entities into a single undo step. If a group is already
+    /// open, the entities are added to it.
+    pub fn begin_group(&mut self, entities : &[Entity]) {
+
+        match &mut self.group {
+            Some(group) => {
+                for entity in entities.iter() {
+                    if !group.entities.contains(entity) {
+                        group.entities.push(*entity);
+                    }
+                }
+            },
+            None => {
+                self.undo.push(Vec::new());
+                self.group = Some(Group { step : self.undo.len() - 1, entities : entities.to_vec() });
+            }
+        }
+    }
+
+    /// Closes the open group, if there is one. A group which collected nothing leaves no step.
+    pub fn end_group(&mut self) {
+
+        if let Some(group) = self.group.take() {
+
+            if self.undo[group.step].is_empty() {
+                self.undo.remove(group.step);
+            }
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.undo.iter().any(|step| !step.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+
+        self.undo.clear();
+        self.redo.clear();
+        self.group = None;
+    }
+
+    fn remap(&mut self, old : &Entity, new : &Entity) {
+
+        for step in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            for edit in step.iter_mut() {
+                edit.remap(old, new);
+            }
+        }
+
+        if let Some(group) = &mut self.group {
+            group.entities.iter_mut().filter(|entity| *entity == old).for_each(|entity| *entity = *new);
+        }
+    }
+}
+
+/// Undoes the latest step. Returns false if there was nothing to undo.
+pub fn undo(state : &mut GameState) -> Result<bool, Error> {
+
+    let step = match state.try_resource_mut::<History>() {
+        Some(history) => {
+            history.end_group();
+            history.undo.pop()
+        },
+        None => None
+    };
+
+    let mut step = match step {
+        Some(step) => step,
+        None => return Ok(false)
+    };
+
+    // Edits are undone in the opposite order they were made.
+    for index in (0..step.len()).rev() {
+
+        if let Err(error) = undo_edit(state, &mut step, index) {
+
+            for done in index + 1..step.len() {
+                redo_edit(state, &mut step, done)?;
+            }
+
+            state.resource_mut::<History>().undo.push(step);
+
+            return Err(error)
+        }
+    }
+
+    state.resource_mut::<History>().redo.push(step);
+
+    Ok(true)
+}
+
+/// Redoes the latest undone step. Returns false if there was nothing to redo.
+pub fn redo(state : &mut GameState) -> Result<bool, Error> {
+
+    let step = match state.try_resource_mut::<History>() {
+        Some(history) => {
+            history.end_group();
+            history.redo.pop()
+        },
+        None => None
+    };
+
+    let mut step = match step {
+        Some(step) => step,
+        None => return Ok(false)
+    };
+
+    for index in 0..step.len() {
+
+        if let Err(error) = redo_edit(state, &mut step, index) {
+
+            for done in (0..index).rev() {
+                undo_edit(state, &mut step, done)?;
+            }
+
+            state.resource_mut::<History>().redo.push(step);
+
+            return Err(error)
+        }
+    }
+
+    state.resource_mut::<History>().undo.push(step);
+
+    Ok(true)
+}
+
+fn undo_edit(state : &mut GameState, step : &mut [Edit], index : usize) -> Result<(), Error> {
+
+    if let Some((old, new)) = step[index].undo(state)? {
+        remap_all(state, step, &old, &new);
+    }
+
+    Ok(())
+}
+
+fn redo_edit(state : &mut GameState, step : &mut [Edit], index : usize) -> Result<(), Error> {
+
+    if let Some((old, new)) = step[index].redo(state)? {
+        remap_all(state, step, &old, &new);
+    }
+
+    Ok(())
+}
+
+/// Points the step being applied and the rest of the history at an entity's new id.
+fn remap_all(state : &mut GameState, step : &mut [Edit], old : &Entity, new : &Entity) {
+
+    for edit in step.iter_mut() {
+        edit.remap(old, new);
+    }
+
+    state.resource_mut::<History>().remap(old, new);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ecs::{PositionComponent, ColorComponent};
+    use crate::game_state::GameState;
+    use nalgebra::Vector3;
+
+    fn entity(index : u32) -> Entity {
+        Entity::new(index, 0)
+    }
+
+    fn move_to(entity : &Entity, x : f32) -> Edit {
+        Edit::set(entity, Some(PositionComponent { position : Vector3::new(0.0, 0.0, 0.0) }),
+                  Some(PositionComponent { position : Vector3::new(x, 0.0, 0.0) }))
+    }
+
+    #[test]
+    fn sets_on_grouped_entities_merge_into_one_step() {
+
+        let mut history = History::new();
+
+        history.begin_group(&[entity(0)]);
+        history.record(move_to(&entity(0), 1.0));
+        history.begin_group(&[entity(0)]);
+        history.record(move_to(&entity(0), 2.0));
+        history.record(Edit::set(&entity(0), None, Some(ColorComponent { color : (1.0, 1.0, 1.0, 1.0) })));
+        history.end_group();
+
+        assert_eq!(history.undo.len(), 1);
+        assert_eq!(history.undo[0].len(), 2);
+    }
+
+    #[test]
+    fn other_edits_made_during_a_group_get_their_own_steps() {
+
+        let mut history = History::new();
+
+        history.begin_group(&[entity(0)]);
+        history.record(move_to(&entity(0), 1.0));
+        history.record(move_to(&entity(1), 1.0));
+        history.record(Edit::Spawn { entity : entity(2), data : SceneEntity::default() });
+        history.record(move_to(&entity(0), 2.0));
+        history.end_group();
+
+        assert_eq!(history.undo.iter().map(|step| step.len()).collect::<Vec<usize>>(), vec![1, 1, 1]);
+        assert!(history.undo[0][0].sets_any_of(&[entity(0)]));
+    }
+
+    #[test]
+    fn an_empty_group_leaves_no_step() {
+
+        let mut history = History::new();
+
+        history.record(move_to(&entity(1), 1.0));
+        history.begin_group(&[entity(0)]);
+        history.record(move_to(&entity(1), 2.0));
+        history.end_group();
+
+        assert_eq!(history.undo.len(), 2);
+        assert!(history.undo.iter().all(|step| step.len() == 1));
+    }
+
+    #[test]
+    fn a_step_which_fails_to_undo_stays_on_the_stack() {
+
+        let mut state = GameState::create_initial_state();
+
+        state.insert_resource(History::new());
+
+        let first = GameState::create_entity(&mut state).build();
+
+        state.set_name(&first, "box").unwrap();
+        state.record_edit(Edit::despawn(&state, &first));
+        state.despawn(&first);
+
+        // Bringing the first box back fails while its name is taken.
+        let second = GameState::create_entity(&mut state).build();
+
+        state.set_name(&second, "box").unwrap();
+
+        assert!(state.undo().is_err());
+        assert!(state.resource::<History>().can_undo());
+        assert!(!state.resource::<History>().can_redo());
+        assert_eq!(state.entity_count(), 1);
+
+        state.set_name(&second, "other box").unwrap();
+
+        assert!(state.undo().unwrap());
+        assert_eq!(state.entity_count(), 2);
+        assert!(state.find_by_name("box").is_some());
+    }
+
+    #[test]
+    fn recorded_entity_ids_follow_entities_brought_back() {
+
+        let mut state = GameState::create_initial_state();
+
+        let old = GameState::create_entity(&mut state).build();
+        let new = GameState::create_entity(&mut state).build();
+        let child = GameState::create_entity(&mut state).build();
+
+        let mut edit = Edit::set(&child, Some(ParentComponent { parent : old }), None);
+
+        edit.remap(&old, &new);
+        edit.undo(&mut state).unwrap();
+
+        assert_eq!(state.get::<ParentComponent>(&child).unwrap().parent, new);
+    }
+}
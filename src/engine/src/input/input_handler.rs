@@ -10,23 +10,26 @@ pub struct InputHandler {
     held_keys: HashMap<KeyCode, i32>
 }
 
+impl Default for InputHandler {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl InputHandler {
 
     pub fn new() -> InputHandler {
         InputHandler { keyboard_pressed: vec!(), mouse_pressed: vec!(), held_mouse_buttons: HashMap::new(), held_keys: HashMap::new()}
     }
 
-    pub fn update_input_state(&mut self, pump: &mut sdl2::EventPump) {
+    /// Takes the keys and mouse buttons held down this frame, counting how many frames each has
+    /// been held for.
+    pub fn update_input_state(&mut self, keys : Vec<KeyCode>, buttons : Vec<MouseInput>) {
 
-       self.keyboard_pressed =  pump.keyboard_state().pressed_scancodes()
-           .filter(|scancode| {input::is_registered_input(&input::scancode_to_keycode(scancode))})
-           .map(|scancode| { input::scancode_to_keycode(&scancode)})
-           .collect::<Vec<KeyCode>>();
+        self.keyboard_pressed = keys;
 
-        self.mouse_pressed =  pump.mouse_state().pressed_mouse_buttons()
-            .filter(|mouse| { input::is_registered_mouse_input({&input::sdl_mouse_to_mouse(mouse)})})
-            .map(|mouse| { input::sdl_mouse_to_mouse(&mouse) })
-            .collect::<Vec<MouseInput>>();
+        self.mouse_pressed = buttons;
 
         let size = self.mouse_pressed.len();
 
@@ -35,7 +38,7 @@ impl InputHandler {
             let input = &self.mouse_pressed[index].clone();
 
             if let Some(i) = self.held_mouse_buttons.get_mut(input) {
-                *i = *i + 1;
+                *i += 1;
             } else {
                 self.held_mouse_buttons.insert(*input, 1);
             }
@@ -48,7 +51,7 @@ impl InputHandler {
             let input = &self.keyboard_pressed[index].clone();
 
             if let Some(i) = self.held_keys.get_mut(input) {
-                *i = *i + 1;
+                *i += 1;
             } else {
                 self.held_keys.insert(*input, 1);
             }
@@ -77,7 +80,7 @@ impl InputHandler {
     pub fn get_keycode(&self, key : &KeyCode) -> bool {
 
         if let Some(count) = self.held_keys.get(key) {
-            return count == &1
+            count == &1
         } else {
             false
         }
@@ -86,7 +89,7 @@ impl InputHandler {
     pub fn get_mouse_button(&self, button : &MouseInput) -> bool {
 
         if let Some(count) = self.held_mouse_buttons.get(button) {
-            return count == &1
+            count == &1
         } else {
             false
         }
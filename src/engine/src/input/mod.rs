@@ -1,16 +1,44 @@
+use crate::game_state::GameState;
+use crate::input::input_handler::InputHandler;
 use nalgebra::Vector2;
+use serde::{Serialize, Deserialize};
 
 pub mod input_handler;
+pub mod recording;
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+/// World resource holding the cursor position for the current frame, both in window (screen)
+/// coordinates and in world coordinates as seen through the active camera.
+#[derive(Clone, Copy)]
+pub struct CursorPosition {
+
+    pub screen : Vector2<f32>,
+    pub world : Vector2<f32>
+}
+
+impl Default for CursorPosition {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CursorPosition {
+
+    pub fn new() -> CursorPosition {
+        CursorPosition { screen : Vector2::new(0.0, 0.0), world : Vector2::new(0.0, 0.0) }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum KeyCode {
 
     W, A, S, D,
     Up, Down, Left, Right,
     Space,
+    Z, Y, P, Delete,
     NA
 }
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum MouseInput {
     LeftMouse, RightMouse,
     MiddleMouse,
@@ -33,6 +61,11 @@ pub fn scancode_to_keycode(scancode: &sdl2::keyboard::Scancode) -> KeyCode {
 
         sdl2::keyboard::Scancode::Space => KeyCode::Space,
 
+        sdl2::keyboard::Scancode::Z => KeyCode::Z,
+        sdl2::keyboard::Scancode::Y => KeyCode::Y,
+        sdl2::keyboard::Scancode::P => KeyCode::P,
+        sdl2::keyboard::Scancode::Delete => KeyCode::Delete,
+
         _ => KeyCode::NA
     }
 }
@@ -58,6 +91,42 @@ pub fn is_registered_mouse_input(code : &MouseInput) -> bool {
     code != &MouseInput::NA
 }
 
+/// Every registered key currently held down.
+pub fn pressed_keys(pump : &sdl2::EventPump) -> Vec<KeyCode> {
+
+    pump.keyboard_state().pressed_scancodes()
+        .map(|scancode| scancode_to_keycode(&scancode))
+        .filter(is_registered_input)
+        .collect()
+}
+
+/// Every registered mouse button currently held down.
+pub fn pressed_mouse_buttons(pump : &sdl2::EventPump) -> Vec<MouseInput> {
+
+    pump.mouse_state().pressed_mouse_buttons()
+        .map(|button| sdl_mouse_to_mouse(&button))
+        .filter(is_registered_mouse_input)
+        .collect()
+}
+
+/// Run condition helper - true while the button is held down.
+pub fn mouse_down(state : &GameState, button : MouseInput) -> bool {
+
+    state.resource::<InputHandler>().get_mouse_down(&button)
+}
+
+/// Run condition helper - true only on the frame the button was first pressed.
+pub fn mouse_pressed(state : &GameState, button : MouseInput) -> bool {
+
+    state.resource::<InputHandler>().get_mouse_button(&button)
+}
+
+/// Run condition helper - true only on the frame the key was first pressed.
+pub fn key_pressed(state : &GameState, key : KeyCode) -> bool {
+
+    state.resource::<InputHandler>().get_keycode(&key)
+}
+
 pub fn get_mouse_coordinates(pump: &sdl2::EventPump) -> Vector2<f32>{
     let state = pump.mouse_state();
     Vector2::new(state.x() as f32, state.y() as f32)
@@ -0,0 +1,196 @@
+//! INPUT RECORDING
+//! Everything the outside world fed into a run, one entry per frame: how long the frame took, which
+//! keys and mouse buttons were released and held, where the cursor was and which window events
+//! arrived. The main loop builds a FrameInput every frame and applies it to the input resources, so
+//! a recording can be replayed into a fresh run (see App::replay) and goes through exactly the same
+//! path.
+//!
+//! Since each frame's length is replayed too, the fixed timestep runs the same number of steps in the
+//! same frames, and the replayed world ends up identical to the recorded one. A recording keeps the
+//! world it finished with, as a scene, so a replay can check it got there.
+//!
+//! Window events are kept for reference only. There's no window to apply them to when replaying, and
+//! nothing in the world depends on them yet.
+
+use crate::ecs::OrthographicCameraComponent;
+use crate::game_state::GameState;
+use crate::input::{KeyCode, MouseInput, CursorPosition};
+use crate::input::input_handler::InputHandler;
+use crate::scene::Scene;
+use crate::time::Time;
+use crate::utilities::camera_utils;
+use failure::{Error, err_msg};
+use nalgebra::Vector2;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::fs;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct InputRecording {
+
+    pub frames : Vec<FrameInput>,
+
+    /// The world after the last frame.
+    pub final_state : Option<Scene>
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct FrameInput {
+
+    /// Real seconds the frame took.
+    pub delta : f32,
+
+    pub released_keys : Vec<KeyCode>,
+    pub released_buttons : Vec<MouseInput>,
+
+    /// Keys and mouse buttons held down.
+    pub keys : Vec<KeyCode>,
+    pub buttons : Vec<MouseInput>,
+
+    /// Cursor position in window coordinates.
+    pub cursor : [f32; 2],
+
+    pub window_events : Vec<WindowInput>
+}
+
+/// The window events the engine reacts to.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum WindowInput {
+
+    Close,
+    Resized(i32, i32),
+    Minimized,
+    Exposed,
+    FocusGained,
+    Enter,
+    TakeFocus
+}
+
+impl WindowInput {
+
+    pub fn from_sdl(event : &sdl2::event::WindowEvent) -> Option<WindowInput> {
+
+        match event {
+            sdl2::event::WindowEvent::Close => Some(WindowInput::Close),
+            sdl2::event::WindowEvent::Resized(x, y) => Some(WindowInput::Resized(*x, *y)),
+            sdl2::event::WindowEvent::Minimized => Some(WindowInput::Minimized),
+            sdl2::event::WindowEvent::Exposed => Some(WindowInput::Exposed),
+            sdl2::event::WindowEvent::FocusGained => Some(WindowInput::FocusGained),
+            sdl2::event::WindowEvent::Enter => Some(WindowInput::Enter),
+            sdl2::event::WindowEvent::TakeFocus => Some(WindowInput::TakeFocus),
+            _ => None
+        }
+    }
+}
+
+impl FrameInput {
+
+    pub fn new() -> FrameInput {
+        FrameInput::default()
+    }
+
+    /// Updates the input handler and cursor position. Time is left alone, as the live loop measures
+    /// it while a replay sets it from delta (see replay).
+    pub fn apply(&self, state : &mut GameState) {
+
+        if let Some(input) = state.try_resource_mut::<InputHandler>() {
+
+            for key in self.released_keys.iter() {
+                input.clear_keyboard_input(key);
+            }
+
+            for button in self.released_buttons.iter() {
+                input.clear_mouse_input(button);
+            }
+
+            input.update_input_state(self.keys.clone(), self.buttons.clone());
+        }
+
+        let screen = Vector2::new(self.cursor[0], self.cursor[1]);
+
+        let world = match state.try_resource::<OrthographicCameraComponent>() {
+            Some(camera) => camera_utils::ortho_screen_to_world_coordinates(camera, screen),
+            None => screen
+        };
+
+        if let Some(cursor) = state.try_resource_mut::<CursorPosition>() {
+            *cursor = CursorPosition { screen, world };
+        }
+    }
+
+    /// Starts a frame of the given length and applies the input, as the live loop did when the frame
+    /// was recorded.
+    pub fn replay(&self, state : &mut GameState) {
+
+        if let Some(time) = state.try_resource_mut::<Time>() {
+            time.advance(self.delta);
+        }
+
+        self.apply(state);
+    }
+}
+
+impl InputRecording {
+
+    pub fn new() -> InputRecording {
+        InputRecording::default()
+    }
+
+    pub fn load(path : &str) -> Result<InputRecording, Error> {
+
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path : &str) -> Result<(), Error> {
+
+        fs::write(path, serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Keeps the world the run finished with.
+    pub fn finish(&mut self, state : &GameState) {
+
+        self.final_state = Some(Scene::from_state(state));
+    }
+
+    /// Fails if the recording kept its final world and the given one doesn't match it. Only what
+    /// Scene::from_state saves is compared - each entity's scene components and their order. Anything
+    /// worked out from those (global transforms, children, collider corners), components scenes
+    /// don't save (e.g. a game's selection) and resources (History, Time and so on) aren't checked.
+    pub fn check(&self, state : &GameState) -> Result<(), Error> {
+
+        // Only the entities are compared, so recordings saved before scenes had a version still match.
+        let mut expected = match &self.final_state {
+            Some(expected) => serde_json::to_value(&expected.entities)?,
+            None => return Ok(())
+        };
+
+        let mut actual = serde_json::to_value(&Scene::from_state(state).entities)?;
+
+        // The game's components are kept as JSON rather than f32s, so numbers read from the file
+        // would otherwise be compared at a different precision to the ones just captured.
+        to_f32_precision(&mut expected);
+        to_f32_precision(&mut actual);
+
+        if actual != expected {
+            return Err(err_msg(format!("The replayed world doesn't match the recording after {} frames.", self.frames.len())))
+        }
+
+        Ok(())
+    }
+}
+
+/// Rounds every non-integer number to the nearest f32, which is what every component stores.
+fn to_f32_precision(value : &mut Value) {
+
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            *value = Value::from(number.as_f64().unwrap() as f32);
+        },
+        Value::Array(values) => values.iter_mut().for_each(to_f32_precision),
+        Value::Object(values) => values.values_mut().for_each(to_f32_precision),
+        _ => ()
+    }
+}
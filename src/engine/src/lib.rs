@@ -4,15 +4,8 @@ extern crate anymap;
 extern crate image;
 extern crate nalgebra;
 extern crate sdl2;
-
-////////////////////////////////////
-//           M A C R O S          //
-////////////////////////////////////
-
-#[macro_use]
-macro_rules! bit {
-    ($value:expr) => {1 << $value};
-}
+extern crate serde;
+extern crate serde_json;
 
 #[macro_use] pub mod events;
 #[macro_use] pub mod window;
@@ -23,6 +16,12 @@ pub mod platform;
 pub mod game_state;
 pub mod input;
 pub mod utilities;
+pub mod time;
+pub mod profiler;
+pub mod scene;
+pub mod prefab;
+pub mod history;
+pub mod headless;
 pub mod application;
 
 
@@ -3,7 +3,8 @@ extern crate gl;
 extern crate failure;
 
 pub struct OpenGLContext {
-    gl_context : sdl2::video::GLContext,
+    // Never read, but the context is deleted when it's dropped.
+    _gl_context : sdl2::video::GLContext,
 }
 
 impl OpenGLContext {
@@ -14,9 +15,9 @@ impl OpenGLContext {
         let gl_context = window.gl_create_context().unwrap();
 
         // Initialise gl.
-        let _gl = gl::load_with(|s| video.gl_get_proc_address(s) as * const std::os::raw::c_void);
+        gl::load_with(|s| video.gl_get_proc_address(s) as * const std::os::raw::c_void);
 
-        OpenGLContext { gl_context }
+        OpenGLContext { _gl_context : gl_context }
     }
 
     pub fn swap_buffers(&mut self, window : &mut sdl2::video::Window) {
@@ -32,7 +33,7 @@ pub fn generate_n_buffers(amount: i32, buffers: Vec<&mut u32>) {
     }
 }
 
-pub fn generate_buffer_data<T>(buffer_type: gl::types::GLenum, buffer : &u32, vertices : &Vec<T>) {
+pub fn generate_buffer_data<T>(buffer_type: gl::types::GLenum, buffer : &u32, vertices : &[T]) {
 
     unsafe {
 
@@ -42,7 +43,7 @@ pub fn generate_buffer_data<T>(buffer_type: gl::types::GLenum, buffer : &u32, ve
         // specified before INTO the buffer we created:
         gl::BufferData(
             buffer_type, // target
-            (vertices.len() * std::mem::size_of::<T>()) as gl::types::GLsizeiptr, // size of data in bytes
+            std::mem::size_of_val(vertices) as gl::types::GLsizeiptr, // size of data in bytes
             vertices.as_ptr() as *const gl::types::GLvoid, // pointer to data
             gl::STATIC_DRAW, // Specifies the object does not change. If it did change,
             // the call would be DYNAMIC_DRAW or STREAM_DRAW,
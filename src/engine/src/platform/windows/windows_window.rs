@@ -15,7 +15,6 @@ use self::sdl2::video::SwapInterval::{VSync, Immediate};
 
 /// Indended to be a window class for a windows implementation. A bit irrelevant at the moment since the window work on mac, but
 /// in future, if the engine is going to be used with the WinAPI, then it'll be more customised.
-
 pub struct WindowsWindow {
 
     window : Window,
@@ -25,10 +24,9 @@ pub struct WindowsWindow {
 }
 
 /// static function for creating a base window (doesnt require specific elements to be inputted by user)
-
 pub fn create_new(properties : WindowProperties, sdl: &Sdl) -> WindowsWindow  {
 
-    WindowsWindow::new(properties, &sdl)
+    WindowsWindow::new(properties, sdl)
 }
 
 // TODO: Work out if this trait system is even needed at this point. Might just remove and replace with a base window struct.
@@ -69,7 +67,6 @@ impl WindowTrait for  WindowsWindow {
 
 /// Just the implementation for the windows window struct. All it has is a base constructor for now,
 /// could be extended to contain added functionality later.
-
 impl WindowsWindow {
 
     fn new(properties : WindowProperties, sdl : &Sdl) -> WindowsWindow {
@@ -91,8 +88,8 @@ impl WindowsWindow {
 
         let data = WindowData {
             title : properties.title.clone(),
-            width : properties.width.clone(),
-            height : properties. height.clone(),
+            width : properties.width,
+            height : properties. height,
             vsync : true,
         };
 
@@ -111,7 +108,6 @@ impl WindowsWindow {
 
 /// A struct which contains base window data. This has the title, width, height, and vsync details
 /// within.
-
 pub struct WindowData {
 
     title : String,
@@ -123,7 +119,6 @@ pub struct WindowData {
 /// This handles all base window events. In its current iteration, the function merely takes in an
 /// sdl2 window event, as well as a window event struct, checks the event for its type and passes in relevant
 /// functions.
-
 pub fn process_event(window_event : &sdl2::event::WindowEvent, event : &mut WindowEvent) {
 
     match window_event {
@@ -140,35 +135,33 @@ pub fn process_event(window_event : &sdl2::event::WindowEvent, event : &mut Wind
              event.events.push_back(Box::new(on_window_resized))
            }
         // TODO
-        sdl2::event::WindowEvent::Minimized => println!("{}", "minimized"),
+        sdl2::event::WindowEvent::Minimized => println!("minimized"),
         // TODO
-        sdl2::event::WindowEvent::Exposed => println!("{}", "exposed"),
+        sdl2::event::WindowEvent::Exposed => println!("exposed"),
         // TODO
-        sdl2::event::WindowEvent::FocusGained => println!("{}", "focus gained"),
+        sdl2::event::WindowEvent::FocusGained => println!("focus gained"),
         // TODO
-        sdl2::event::WindowEvent::Enter => println!("{}", "Mouse entered"),
+        sdl2::event::WindowEvent::Enter => println!("Mouse entered"),
         // TODO
-        sdl2::event::WindowEvent::TakeFocus => println!("{}", "Taking focus"),
+        sdl2::event::WindowEvent::TakeFocus => println!("Taking focus"),
         // TODO
         _ => ()
     }
 }
 
 /// Not sure if this function should be left alone.
-
-#[inline] pub fn on_window_close<'a>(event : &mut WindowsWindow) {
+#[inline] pub fn on_window_close(event : &mut WindowsWindow) {
 
     println!("WINDOW: Window closed, Exiting {}.", event.data.title);
 }
 
 /// Logs the new height and width of the window after a resize has occurred.
 /// TODO: Add more functionality when rendering is actually put into place.
-
 #[inline] pub fn on_window_resized(event : &mut WindowsWindow) {
 
     unsafe {
         gl::Viewport(0, 0, event.data.width as i32, event.data.height as i32);
     }
 
-    println!("{} {} {}", "WINDOW: Resized:", event.data.width, event.data.height);
+    println!("WINDOW: Resized: {} {}", event.data.width, event.data.height);
 }
\ No newline at end of file
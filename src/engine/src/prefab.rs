@@ -0,0 +1,160 @@
+//! PREFABS
+//! A prefab is a named entity template loaded from a JSON file. Its components use the same format
+//! as a scene entity, and any field can be overridden when the prefab is spawned, e.g:
+//!
+//! state.spawn_prefab("crate", json!({ "position": [100.0, 0.0, 0.0] }))
+//!
+//! A prefab can inherit from another prefab, in which case its components are laid over the
+//! parent's. Overrides are merged the same way: objects are merged field by field, anything else
+//! (including null, which removes a component) replaces the inherited value.
+
+use crate::scene::{self, SceneEntity, SCENE_VERSION};
+use failure::{Error, err_msg};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Prefab {
+
+    /// The format the prefab was saved in, shared with scenes. Files without one count as 0.
+    pub version : u32,
+
+    pub inherits : Option<String>,
+    pub components : Value
+}
+
+impl Prefab {
+
+    /// Brings a prefab saved in an older format up to date (see scene::SCENE_VERSION).
+    pub fn migrate(&mut self) -> Result<(), Error> {
+
+        if self.version < 1 {
+            if let Some(velocity) = self.components.get_mut("velocity").filter(|velocity| !velocity.is_null()) {
+                *velocity = serde_json::to_value(scene::per_second(&serde_json::from_value(velocity.clone())?))?;
+            }
+        }
+
+        self.version = SCENE_VERSION;
+
+        Ok(())
+    }
+}
+
+/// World resource holding every prefab, keyed by name.
+pub struct Prefabs {
+
+    prefabs : HashMap<String, Prefab>
+}
+
+impl Default for Prefabs {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Prefabs {
+
+    pub fn new() -> Prefabs {
+
+        Prefabs { prefabs : HashMap::new() }
+    }
+
+    /// Loads every .json file in a directory. Each prefab is named after its file, so
+    /// prefabs/crate.json becomes "crate".
+    pub fn load_dir(path : &str) -> Result<Prefabs, Error> {
+
+        let mut prefabs = Prefabs::new();
+
+        for entry in fs::read_dir(path)? {
+
+            let path = entry?.path();
+
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue
+            }
+
+            prefabs.load(&path)?;
+        }
+
+        Ok(prefabs)
+    }
+
+    /// Loads a single prefab file, named after the file.
+    pub fn load(&mut self, path : &Path) -> Result<(), Error> {
+
+        let name = path.file_stem()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| err_msg(format!("Invalid prefab file name: {}", path.display())))?;
+
+        let mut prefab : Prefab = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        prefab.migrate()?;
+
+        self.insert(name, prefab);
+
+        Ok(())
+    }
+
+    pub fn insert(&mut self, name : &str, prefab : Prefab) {
+
+        self.prefabs.insert(String::from(name), prefab);
+    }
+
+    pub fn get(&self, name : &str) -> Option<&Prefab> {
+
+        self.prefabs.get(name)
+    }
+
+    /// Builds the entity a prefab describes, with the overrides applied on top. Pass Value::Null
+    /// for no overrides.
+    pub fn build(&self, name : &str, overrides : &Value) -> Result<SceneEntity, Error> {
+
+        let mut components = self.resolve(name, &mut Vec::new())?;
+
+        if !overrides.is_null() {
+            merge(&mut components, overrides);
+        }
+
+        Ok(serde_json::from_value(components)?)
+    }
+
+    /// Merges a prefab's components over everything it inherits.
+    fn resolve(&self, name : &str, visited : &mut Vec<String>) -> Result<Value, Error> {
+
+        if visited.iter().any(|visited| visited == name) {
+            return Err(err_msg(format!("Prefab {} inherits from itself.", name)))
+        }
+
+        visited.push(String::from(name));
+
+        let prefab = self.get(name).ok_or_else(|| err_msg(format!("No prefab exists named {}", name)))?;
+
+        let mut components = match &prefab.inherits {
+            Some(parent) => self.resolve(parent, visited)?,
+            None => Value::Object(Default::default())
+        };
+
+        merge(&mut components, &prefab.components);
+
+        Ok(components)
+    }
+}
+
+/// Lays the overrides over the base. Objects are merged key by key; any other value replaces
+/// whatever was there.
+fn merge(base : &mut Value, overrides : &Value) {
+
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides.iter() {
+                merge(base.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        },
+        (base, overrides) => *base = overrides.clone()
+    }
+}
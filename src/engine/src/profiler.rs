@@ -0,0 +1,307 @@
+//! PROFILER
+//! World resource which times the main loop. The schedule times every system and stage it runs, and
+//! the main loop times the event pump and buffer swap with ProfileScope. Nothing is timed unless the
+//! resource has been inserted.
+//!
+//! Spans are grouped by frame (begin_frame ... end_frame). Each frame's total per span name is kept
+//! for the last FRAME_WINDOW frames, which summary turns into min/avg/max timings. A name which ran
+//! more than once in a frame (e.g. a FixedUpdate system) counts the sum of its runs.
+//!
+//! While a capture is running every span is also kept as it was recorded, and can be written out in
+//! Chrome's trace_event JSON format, which chrome://tracing and Perfetto can open.
+
+use crate::game_state::GameState;
+use failure::Error;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+pub struct Profiler {
+
+    epoch : Instant,
+    frame_start : Option<Instant>,
+    frame : Vec<Span>,
+    history : HashMap<String, VecDeque<Duration>>,
+    frame_count : usize,
+    capture : Option<Vec<Span>>,
+    threads : Vec<ThreadId>
+}
+
+/// A single timed piece of work.
+#[derive(Clone, Debug)]
+pub struct Span {
+
+    pub name : String,
+    pub category : &'static str,
+
+    /// Index of the thread it ran on, 0 being the first thread to record anything (the main thread).
+    pub thread : usize,
+
+    /// Time since the profiler was created.
+    pub start : Duration,
+    pub duration : Duration
+}
+
+/// Timings for one span name over the last few frames.
+#[derive(Clone, Debug)]
+pub struct Timings {
+
+    pub name : String,
+    pub min : Duration,
+    pub avg : Duration,
+    pub max : Duration
+}
+
+/// Times whatever happens between start and end, e.g:
+///
+/// let scope = ProfileScope::start("event_pump");
+/// ...
+/// scope.end(&mut state);
+pub struct ProfileScope {
+
+    name : &'static str,
+    start : Instant
+}
+
+/// How many frames the rolling summary covers.
+pub const FRAME_WINDOW : usize = 120;
+
+/// The span which covers each whole frame.
+const FRAME : &str = "frame";
+
+impl Default for Profiler {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+
+    pub fn new() -> Profiler {
+
+        Profiler {
+            epoch : Instant::now(),
+            frame_start : None,
+            frame : Vec::new(),
+            history : HashMap::new(),
+            frame_count : 0,
+            capture : None,
+            threads : Vec::new()
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+
+        self.frame_start = Some(Instant::now());
+    }
+
+    /// Closes the frame, adding its totals to the rolling summary.
+    pub fn end_frame(&mut self) {
+
+        if let Some(start) = self.frame_start.take() {
+            self.record(FRAME, "frame", start, Instant::now());
+        }
+
+        let mut totals : HashMap<&str, Duration> = HashMap::new();
+
+        for span in self.frame.iter() {
+            *totals.entry(&span.name).or_insert(Duration::from_secs(0)) += span.duration;
+        }
+
+        // Names which didn't run this frame are counted as zero, so every window covers the same frames.
+        for (name, frames) in self.history.iter_mut() {
+            frames.push_back(totals.remove(name.as_str()).unwrap_or(Duration::from_secs(0)));
+        }
+
+        for (name, total) in totals {
+            let mut frames = VecDeque::with_capacity(FRAME_WINDOW);
+            frames.extend((0..self.frame_count.min(FRAME_WINDOW)).map(|_| Duration::from_secs(0)));
+            frames.push_back(total);
+            self.history.insert(String::from(name), frames);
+        }
+
+        for frames in self.history.values_mut() {
+            while frames.len() > FRAME_WINDOW {
+                frames.pop_front();
+            }
+        }
+
+        self.frame_count += 1;
+
+        match &mut self.capture {
+            Some(capture) => capture.append(&mut self.frame),
+            None => self.frame.clear()
+        }
+    }
+
+    /// Records a span which ran on the calling thread.
+    pub fn record(&mut self, name : &str, category : &'static str, start : Instant, end : Instant) {
+
+        self.record_on(thread::current().id(), name, category, start, end);
+    }
+
+    /// Records a span which ran on another thread.
+    pub fn record_on(&mut self, thread : ThreadId, name : &str, category : &'static str, start : Instant, end : Instant) {
+
+        let thread = match self.threads.iter().position(|known| *known == thread) {
+            Some(index) => index,
+            None => {
+                self.threads.push(thread);
+                self.threads.len() - 1
+            }
+        };
+
+        self.frame.push(Span {
+            name : String::from(name),
+            category,
+            thread,
+            start : start.duration_since(self.epoch),
+            duration : end.duration_since(start)
+        });
+    }
+
+    /// Min/avg/max per frame for every span name over the last FRAME_WINDOW frames, slowest average
+    /// first.
+    pub fn summary(&self) -> Vec<Timings> {
+
+        let mut summary = self.history.iter()
+            .filter(|(_, frames)| !frames.is_empty())
+            .map(|(name, frames)| Timings {
+                name : name.clone(),
+                min : *frames.iter().min().unwrap(),
+                avg : frames.iter().sum::<Duration>() / frames.len() as u32,
+                max : *frames.iter().max().unwrap()
+            })
+            .collect::<Vec<Timings>>();
+
+        summary.sort_by_key(|timings| std::cmp::Reverse(timings.avg));
+
+        summary
+    }
+
+    /// Prints the summary as a table.
+    pub fn print_summary(&self) {
+
+        println!("{:<32} {:>10} {:>10} {:>10}", "span (ms per frame)", "min", "avg", "max");
+
+        for timings in self.summary() {
+            println!("{:<32} {:>10.3} {:>10.3} {:>10.3}", timings.name, millis(timings.min), millis(timings.avg), millis(timings.max));
+        }
+    }
+
+    /// Starts keeping every span. Restarts the capture if one is already running.
+    pub fn start_capture(&mut self) {
+
+        self.capture = Some(Vec::new());
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Stops the capture, returning every span recorded since it started. Spans from a frame which
+    /// hasn't ended yet aren't included.
+    pub fn stop_capture(&mut self) -> Vec<Span> {
+
+        self.capture.take().unwrap_or_default()
+    }
+
+    /// Stops the capture and writes it to a file in Chrome's trace_event format.
+    pub fn write_capture(&mut self, path : &str) -> Result<usize, Error> {
+
+        let spans = self.stop_capture();
+
+        fs::write(path, to_trace(&spans, self.threads.len())?)?;
+
+        Ok(spans.len())
+    }
+}
+
+/// Converts spans to Chrome's trace_event JSON format, one complete ("X") event per span plus a name
+/// for each thread.
+pub fn to_trace(spans : &[Span], thread_count : usize) -> Result<String, Error> {
+
+    let names = (0..thread_count).map(|thread| TraceEvent {
+        name : "thread_name",
+        cat : "",
+        ph : "M",
+        ts : 0.0,
+        dur : None,
+        pid : 1,
+        tid : thread,
+        args : Some(TraceArgs { name : if thread == 0 { String::from("main") } else { format!("worker {}", thread) } })
+    });
+
+    let events = spans.iter().map(|span| TraceEvent {
+        name : &span.name,
+        cat : span.category,
+        ph : "X",
+        ts : micros(span.start),
+        dur : Some(micros(span.duration)),
+        pid : 1,
+        tid : span.thread,
+        args : None
+    });
+
+    Ok(serde_json::to_string(&Trace { trace_events : names.chain(events).collect() })?)
+}
+
+#[derive(Serialize)]
+struct Trace<'a> {
+
+    #[serde(rename = "traceEvents")]
+    trace_events : Vec<TraceEvent<'a>>
+}
+
+#[derive(Serialize)]
+struct TraceEvent<'a> {
+
+    name : &'a str,
+    cat : &'a str,
+    ph : &'a str,
+
+    /// Microseconds.
+    ts : f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur : Option<f64>,
+
+    pid : u32,
+    tid : usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args : Option<TraceArgs>
+}
+
+#[derive(Serialize)]
+struct TraceArgs {
+
+    name : String
+}
+
+fn micros(duration : Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+fn millis(duration : Duration) -> f64 {
+    duration.as_secs_f64() * 1_000.0
+}
+
+impl ProfileScope {
+
+    pub fn start(name : &'static str) -> ProfileScope {
+
+        ProfileScope { name, start : Instant::now() }
+    }
+
+    /// Records the scope with the state's profiler, if it has one.
+    pub fn end(self, state : &mut GameState) {
+
+        let end = Instant::now();
+
+        if let Some(profiler) = state.try_resource_mut::<Profiler>() {
+            profiler.record(self.name, "main_loop", self.start, end);
+        }
+    }
+}
@@ -1,3 +1,9 @@
 #[macro_use] pub mod shaders;
 pub mod practice;
-#[macro_use] pub mod shapes;
\ No newline at end of file
+#[macro_use] pub mod shapes;
+/// True once a window has created a GL context and loaded the GL functions. When running headless
+/// nothing has, so components are built with empty GL handles and nothing is drawn.
+pub fn gl_loaded() -> bool {
+
+    gl::CreateProgram::is_loaded()
+}
@@ -10,8 +10,8 @@ use failure::Error;
 // Macro for creating a key typed event.
 macro_rules! triangle_render {
     () => {{
-    use crate::renderer::shaders::shader_program::*;
-    use crate::renderer::shaders::shader::*;
+    use $crate::renderer::shaders::shader_program::*;
+    use $crate::renderer::shaders::shader::*;
     use std::ffi::{CString};
 
         let program = ShaderProgram::from_shaders(&[Shader::from_vert_source(
@@ -111,10 +111,14 @@ impl ShaderProgram {
         }
     }
 
+    /// Sets the named bool uniform on this program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_bool(&self, value : bool, name : &str) -> Result<(), Error> {
         let condition : u32 = match value {
-            true => (1),
-            false => (0)
+            true => 1,
+            false => 0
         };
 
         gl::Uniform1ui(gl::GetUniformLocation(self.id(), CString::new(name)?.as_ptr()), condition);
@@ -122,6 +126,10 @@ impl ShaderProgram {
         Ok(())
     }
 
+    /// Sets the named vec4 uniform on this program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_vector4(&self, name : &str, vec : (f32, f32, f32, f32)) -> Result<(), Error>{
 
         gl::Uniform4f(gl::GetUniformLocation(self.id(), CString::new(name)?.as_ptr()), vec.0, vec.1, vec.2, vec.3);
@@ -129,6 +137,10 @@ impl ShaderProgram {
         Ok(())
     }
 
+    /// Sets the named vec2 uniform on this program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_vector2(&self, name : &str, vec : (f32, f32)) -> Result<(), Error> {
 
         gl::Uniform2f(gl::GetUniformLocation(self.id(), CString::new(name)?.as_ptr()), vec.0, vec.1);
@@ -136,6 +148,10 @@ impl ShaderProgram {
         Ok(())
     }
 
+    /// Sets the named int uniform on this program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_int(&self, name : &str, number : i32) -> Result<(), Error> {
 
         gl::Uniform1i(gl::GetUniformLocation(self.id(), CString::new(name)?.as_ptr()), number);
@@ -143,6 +159,10 @@ impl ShaderProgram {
         Ok(())
     }
 
+    /// Sets the named float uniform on this program, which has to be in use.
+    ///
+    /// # Safety
+    /// GL has to be loaded, with its context current on this thread.
     pub unsafe fn set_float(&self, name : &str, number : f32) -> Result<(), Error> {
 
         gl::Uniform1f(gl::GetUniformLocation(self.id(), CString::new(name)?.as_ptr()), number);
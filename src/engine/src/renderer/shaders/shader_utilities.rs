@@ -5,10 +5,10 @@ use std::ffi::{CString};
 pub fn create_whitespace_cstring_with_len(len: usize) -> CString {
 
     // Allocate buffer to correct size
-    let mut buffer: Vec<u8> = Vec::with_capacity(len as usize + 1);
+    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
 
     // Fill buffer with len spaces
-    buffer.extend([b' '].iter().cycle().take(len as usize));
+    buffer.extend([b' '].iter().cycle().take(len));
 
     // Convert buffer to Cstring
     unsafe { CString::from_vec_unchecked(buffer) }
@@ -4,7 +4,7 @@ extern crate gl;
 // Macro for creating a key typed event.
 macro_rules! quad { () => {{
 
-        use crate::platform::open_gl::*;
+        use $crate::platform::open_gl::*;
 
         let mut vertex_array_object: gl::types::GLuint = 0;
         let mut vertex_buffer_object: gl::types::GLuint = 0;
@@ -0,0 +1,491 @@
+//! SCENES
+//! A scene is a plain-data copy of every entity in the world, which can be written to and read from
+//! JSON. Anything backed by a GL handle is stored as a reference to the asset it was built from
+//! (shader and mesh names, texture paths) and rebuilt when the scene is spawned. Values which are
+//! always worked out from others (collider corners, global transforms, children lists) are not
+//! stored.
+//!
+//! Games can add their own components to the format by implementing SceneComponent and registering
+//! it with GameState::register_scene_component. Those are stored next to the engine's, under the
+//! name they were registered with.
+
+use crate::ecs::{PositionComponent, RotationComponent, RotationUpdateComponent, ScaleComponent, ColorComponent,
+                 VelocityComponent, RenderComponent, TextureMixComponent, TextureUpdateComponent,
+                 BoxCollider2DComponent, ParentComponent, TagsComponent, Texture, Component};
+use crate::game_state::{GameState, Entity};
+use crate::utilities::vector_utils::{get_box_corners, get_rotated_corners};
+use failure::{Error, err_msg};
+use nalgebra::{Vector2, Vector3};
+use serde::{Serialize, Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Scene {
+
+    /// The format the scene was saved in. Files from before versioning count as 0.
+    #[serde(default)]
+    pub version : u32,
+
+    pub entities : Vec<SceneEntity>
+}
+
+/// The current scene (and prefab) format.
+/// 1 - velocities are in units per second. Version 0 stored them in units per frame at 144fps.
+pub const SCENE_VERSION : u32 = 1;
+
+/// The frame rate version 0 velocities were measured at.
+const VERSION_0_FRAME_RATE : f32 = 144.0;
+
+/// A single entity. Every component is optional.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct SceneEntity {
+
+    pub name : Option<String>,
+    pub tags : Vec<String>,
+    pub parent : Option<SceneReference>,
+    pub position : Option<[f32; 3]>,
+    pub rotation : Option<[f32; 3]>,
+    pub rotation_update : Option<SceneRotationUpdate>,
+    pub scale : Option<[f32; 3]>,
+    pub velocity : Option<[f32; 3]>,
+    pub color : Option<(f32, f32, f32, f32)>,
+    pub render : Option<SceneRender>,
+    pub texture_mix : Option<SceneTextureMix>,
+    pub texture_update : Option<f32>,
+    pub box_collider : Option<SceneBoxCollider>,
+
+    /// The game's components, keyed by the name they were registered under. Null counts as missing,
+    /// and is dropped when the entity is read.
+    #[serde(flatten, deserialize_with = "without_nulls")]
+    pub components : BTreeMap<String, Value>
+}
+
+/// A component from outside the engine which can be saved in scenes and prefabs.
+/// SEE: GameState::register_scene_component
+pub trait SceneComponent : Component {
+
+    fn to_scene(&self) -> Value;
+
+    fn from_scene(value : Value) -> Result<Self, Error>;
+}
+
+/// Refers to another entity, either by its position in the scene's entity list or by name. A name
+/// can belong to an entity in the same scene or one already in the world.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum SceneReference {
+
+    Index(usize),
+    Name(String)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SceneRotationUpdate {
+
+    pub axis : [f32; 3],
+    pub angle : f32
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SceneRender {
+
+    pub shader : String,
+    pub mesh : String
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SceneTextureMix {
+
+    pub textures : Vec<SceneTexture>,
+    pub opacity : f32
+}
+
+/// Textures are bound to texture units in the order they are listed.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SceneTexture {
+
+    pub path : String,
+    pub uniform_name : String
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SceneBoxCollider {
+
+    pub position : [f32; 2],
+    pub size : [f32; 2]
+}
+
+impl Scene {
+
+    /// Reads a scene from a JSON file.
+    pub fn load(path : &str) -> Result<Scene, Error> {
+
+        let json = fs::read_to_string(path)?;
+
+        let mut scene : Scene = serde_json::from_str(&json)?;
+
+        scene.migrate();
+
+        Ok(scene)
+    }
+
+    /// Brings a scene saved in an older format up to date.
+    pub fn migrate(&mut self) {
+
+        if self.version < 1 {
+            for entity in self.entities.iter_mut() {
+                if let Some(velocity) = &mut entity.velocity {
+                    *velocity = per_second(velocity);
+                }
+            }
+        }
+
+        self.version = SCENE_VERSION;
+    }
+
+    /// Writes the scene to a JSON file.
+    pub fn save(&self, path : &str) -> Result<(), Error> {
+
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Copies every live entity in the world into a scene.
+    pub fn from_state(state : &GameState) -> Scene {
+
+        let entities = state.entities().collect::<Vec<Entity>>();
+
+        let indices = entities.iter()
+            .enumerate()
+            .map(|(index, entity)| (*entity, index))
+            .collect::<HashMap<Entity, usize>>();
+
+        let entities = entities.iter()
+            .map(|entity| SceneEntity::from_entity(state, entity, &indices))
+            .collect();
+
+        Scene { version : SCENE_VERSION, entities }
+    }
+
+    /// Spawns every entity in the scene into the world, rebuilding GL handles from asset references.
+    /// Returns the spawned entities, in the same order as the scene. If anything fails, every entity
+    /// spawned so far is despawned again before the error is returned.
+    pub fn spawn(&self, state : &mut GameState) -> Result<Vec<Entity>, Error> {
+
+        let mut spawned = Vec::with_capacity(self.entities.len());
+
+        match self.spawn_into(state, &mut spawned) {
+            Ok(()) => Ok(spawned),
+            Err(error) => {
+
+                for entity in spawned.iter() {
+                    state.despawn(entity);
+                }
+
+                Err(error)
+            }
+        }
+    }
+
+    fn spawn_into(&self, state : &mut GameState, spawned : &mut Vec<Entity>) -> Result<(), Error> {
+
+        for entity in self.entities.iter() {
+            spawned.push(entity.spawn(state)?);
+        }
+
+        for (index, entity) in self.entities.iter().enumerate() {
+
+            if let Some(parent) = &entity.parent {
+
+                let parent = match parent {
+                    SceneReference::Index(parent) => spawned.get(*parent).cloned(),
+                    SceneReference::Name(name) => state.find_by_name(name)
+                };
+
+                let parent = parent
+                    .ok_or_else(|| err_msg(format!("Scene entity {} has a parent which doesn't exist.", index)))?;
+
+                state.set_parent(&spawned[index], &parent)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl SceneEntity {
+
+    /// Copies a single entity. Its parent is left out, since a parent can only be referred to from
+    /// within a scene.
+    pub fn capture(state : &GameState, entity : &Entity) -> SceneEntity {
+
+        SceneEntity::from_entity(state, entity, &HashMap::new())
+    }
+
+    fn from_entity(state : &GameState, entity : &Entity, indices : &HashMap<Entity, usize>) -> SceneEntity {
+
+        SceneEntity {
+            name : state.name_of(entity).map(String::from),
+            tags : state.get::<TagsComponent>(entity).map_or(Vec::new(), |tags| tags.tags.clone()),
+            parent : state.get::<ParentComponent>(entity)
+                .and_then(|parent| indices.get(&parent.parent))
+                .map(|index| SceneReference::Index(*index)),
+            position : state.get::<PositionComponent>(entity).map(|position| to_array_3(&position.position)),
+            rotation : state.get::<RotationComponent>(entity).map(|rotation| to_array_3(&rotation.rotation)),
+            rotation_update : state.get::<RotationUpdateComponent>(entity).map(|update| SceneRotationUpdate {
+                axis : to_array_3(&update.axis),
+                angle : update.angle
+            }),
+            scale : state.get::<ScaleComponent>(entity).map(|scale| to_array_3(&scale.scale)),
+            velocity : state.get::<VelocityComponent>(entity).map(|velocity| to_array_3(&velocity.velocity)),
+            color : state.get::<ColorComponent>(entity).map(|color| color.color),
+            render : state.get::<RenderComponent>(entity).map(|render| SceneRender {
+                shader : render.shader.clone(),
+                mesh : render.mesh.clone()
+            }),
+            texture_mix : state.get::<TextureMixComponent>(entity).map(|texture_mix| SceneTextureMix {
+                textures : texture_mix.textures.iter()
+                    .map(|texture| SceneTexture { path : texture.path.clone(), uniform_name : texture.uniform_name.clone() })
+                    .collect(),
+                opacity : texture_mix.opacity
+            }),
+            texture_update : state.get::<TextureUpdateComponent>(entity).map(|update| update.opacity_change),
+            box_collider : state.get::<BoxCollider2DComponent>(entity).map(|collider| SceneBoxCollider {
+                position : [collider.position.x, collider.position.y],
+                size : [collider.size.x, collider.size.y]
+            }),
+            components : state.registry.scene_components()
+                .filter_map(|(name, info)| (info.capture)(state, entity).map(|value| (String::from(*name), value)))
+                .collect()
+        }
+    }
+
+    /// Spawns the entity on its own. The parent is left for Scene::spawn to connect. If a component
+    /// fails to build, the entity is despawned again before the error is returned.
+    pub fn spawn(&self, state : &mut GameState) -> Result<Entity, Error> {
+
+        let entity = GameState::create_entity(state).build();
+
+        match self.build(state, &entity) {
+            Ok(()) => Ok(entity),
+            Err(error) => {
+                state.despawn(&entity);
+                Err(error)
+            }
+        }
+    }
+
+    fn build(&self, state : &mut GameState, entity : &Entity) -> Result<(), Error> {
+
+        if let Some(name) = &self.name {
+            state.set_name(entity, name)?;
+        }
+
+        for tag in self.tags.iter() {
+            state.add_tag(entity, tag);
+        }
+
+        if let Some(position) = self.position {
+            state.add_component_to(PositionComponent { position : from_array_3(&position) }, entity);
+        }
+
+        if let Some(rotation) = self.rotation {
+            state.add_component_to(RotationComponent { rotation : from_array_3(&rotation) }, entity);
+        }
+
+        if let Some(update) = &self.rotation_update {
+            state.add_component_to(RotationUpdateComponent { axis : from_array_3(&update.axis), angle : update.angle }, entity);
+        }
+
+        if let Some(scale) = self.scale {
+            state.add_component_to(ScaleComponent { scale : from_array_3(&scale) }, entity);
+        }
+
+        if let Some(velocity) = self.velocity {
+            state.add_component_to(VelocityComponent { velocity : from_array_3(&velocity) }, entity);
+        }
+
+        if let Some(color) = self.color {
+            state.add_component_to(ColorComponent { color }, entity);
+        }
+
+        if let Some(render) = &self.render {
+            state.add_component_to(RenderComponent::from_assets(&render.shader, &render.mesh)?, entity);
+        }
+
+        if let Some(texture_mix) = &self.texture_mix {
+            state.add_component_to(TextureMixComponent { textures : load_textures(&texture_mix.textures)?, opacity : texture_mix.opacity }, entity);
+        }
+
+        if let Some(opacity_change) = self.texture_update {
+            state.add_component_to(TextureUpdateComponent { opacity_change }, entity);
+        }
+
+        if let Some(collider) = &self.box_collider {
+
+            let position = Vector2::new(collider.position[0], collider.position[1]);
+            let size = Vector2::new(collider.size[0], collider.size[1]);
+            let angle = self.rotation.map_or(0.0, |rotation| rotation[2]);
+
+            let corners = get_rotated_corners(get_box_corners(position, size), position, angle);
+
+            state.add_component_to(BoxCollider2DComponent { position, size, corners }, entity);
+        }
+
+        for (name, value) in self.components.iter() {
+
+            let info = *state.registry.scene_component(name)
+                .ok_or_else(|| err_msg(format!("{} is not a scene component.", name)))?;
+
+            (info.spawn)(state, entity, value.clone())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn without_nulls<'de, D : Deserializer<'de>>(deserializer : D) -> Result<BTreeMap<String, Value>, D::Error> {
+
+    let mut components = BTreeMap::<String, Value>::deserialize(deserializer)?;
+
+    components.retain(|_, value| !value.is_null());
+
+    Ok(components)
+}
+
+fn load_textures(textures : &[SceneTexture]) -> Result<Vec<Texture>, Error> {
+
+    let mut loaded = Vec::with_capacity(textures.len());
+
+    for (number, texture) in textures.iter().enumerate() {
+        loaded.push(texture!(texture.path.as_str(), number as i32, gl::TEXTURE0 + number as u32, texture.uniform_name.clone()));
+    }
+
+    Ok(loaded)
+}
+
+/// Converts a version 0 velocity, in units per frame, to units per second.
+pub fn per_second(velocity : &[f32; 3]) -> [f32; 3] {
+    [velocity[0] * VERSION_0_FRAME_RATE, velocity[1] * VERSION_0_FRAME_RATE, velocity[2] * VERSION_0_FRAME_RATE]
+}
+
+fn to_array_3(vector : &Vector3<f32>) -> [f32; 3] {
+    [vector.x, vector.y, vector.z]
+}
+
+fn from_array_3(array : &[f32; 3]) -> Vector3<f32> {
+    Vector3::new(array[0], array[1], array[2])
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn broken_entity() -> SceneEntity {
+        SceneEntity {
+            name : Some(String::from("broken")),
+            position : Some([1.0, 2.0, 0.0]),
+            render : Some(SceneRender { shader : String::from("missing"), mesh : String::from("quad") }),
+            ..SceneEntity::default()
+        }
+    }
+
+    #[test]
+    fn failed_entity_spawn_leaves_nothing_behind() {
+
+        let mut state = GameState::create_initial_state();
+
+        assert!(broken_entity().spawn(&mut state).is_err());
+        assert_eq!(state.entity_count(), 0);
+        assert_eq!(state.find_by_name("broken"), None);
+    }
+
+    #[test]
+    fn failed_scene_spawn_despawns_earlier_entities() {
+
+        let mut state = GameState::create_initial_state();
+
+        let parent = SceneEntity { name : Some(String::from("parent")), ..SceneEntity::default() };
+        let orphan = SceneEntity { parent : Some(SceneReference::Index(5)), ..SceneEntity::default() };
+
+        assert!(Scene { version : SCENE_VERSION, entities : vec![parent.clone(), orphan] }.spawn(&mut state).is_err());
+        assert_eq!(state.entity_count(), 0);
+        assert_eq!(state.find_by_name("parent"), None);
+
+        assert!(Scene { version : SCENE_VERSION, entities : vec![parent, broken_entity()] }.spawn(&mut state).is_err());
+        assert_eq!(state.entity_count(), 0);
+    }
+
+    #[test]
+    fn version_0_velocities_are_migrated_to_per_second() {
+
+        let mut scene : Scene = serde_json::from_str(r#"{ "entities": [{ "velocity": [1.0, -0.5, 0.0] }, {}] }"#).unwrap();
+
+        scene.migrate();
+
+        assert_eq!(scene.version, SCENE_VERSION);
+        assert_eq!(scene.entities[0].velocity, Some([144.0, -72.0, 0.0]));
+        assert_eq!(scene.entities[1].velocity, None);
+
+        // Migrating again changes nothing.
+        scene.migrate();
+
+        assert_eq!(scene.entities[0].velocity, Some([144.0, -72.0, 0.0]));
+    }
+
+    struct HealthComponent {
+
+        health : f32
+    }
+
+    impl Component for HealthComponent {}
+
+    impl SceneComponent for HealthComponent {
+
+        fn to_scene(&self) -> Value {
+            Value::from(self.health)
+        }
+
+        fn from_scene(value : Value) -> Result<Self, Error> {
+            Ok(HealthComponent { health : serde_json::from_value(value)? })
+        }
+    }
+
+    #[test]
+    fn scene_components_are_saved_and_spawned_by_name() {
+
+        let mut state = GameState::create_initial_state();
+
+        state.register_scene_component::<HealthComponent>("health");
+
+        let scene : Scene = serde_json::from_str(r#"{ "entities": [{ "health": 5.0 }, { "health": null }] }"#).unwrap();
+
+        assert!(scene.entities[1].components.is_empty());
+
+        let entities = scene.spawn(&mut state).unwrap();
+
+        assert_eq!(state.get::<HealthComponent>(&entities[0]).map(|health| health.health), Some(5.0));
+        assert!(!state.has::<HealthComponent>(&entities[1]));
+
+        let saved = SceneEntity::capture(&state, &entities[0]);
+
+        assert_eq!(saved.components.get("health"), Some(&Value::from(5.0)));
+        assert!(SceneEntity::capture(&state, &entities[1]).components.is_empty());
+    }
+
+    #[test]
+    fn unregistered_scene_components_fail_to_spawn() {
+
+        let mut state = GameState::create_initial_state();
+
+        let entity : SceneEntity = serde_json::from_str(r#"{ "helth": 5.0 }"#).unwrap();
+
+        assert!(entity.spawn(&mut state).is_err());
+        assert_eq!(state.entity_count(), 0);
+    }
+}
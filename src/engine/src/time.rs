@@ -0,0 +1,117 @@
+//! TIME
+//! World resource which stores frame timing. The main loop updates it once at the start of every
+//! frame, so any system can read how long the last frame took.
+//!
+//! The simulation runs in fixed steps of fixed_delta seconds (the FixedUpdate stage), however long
+//! frames take. Each frame's delta is added to an accumulator, and the schedule runs as many fixed
+//! steps as the accumulator holds. Whatever is left over is how far the world is between the last
+//! step and the next one, which rendering uses to interpolate (see alpha).
+//!
+//! time_scale speeds up or slows down everything driven by delta and the fixed steps - 0.0 pauses
+//! the simulation. Systems in FixedUpdate should use fixed_delta rather than delta.
+
+use std::time::{Duration, Instant};
+
+pub struct Time {
+
+    /// Seconds the last frame took, scaled by time_scale.
+    pub delta : f32,
+
+    /// Seconds the last frame took in real time.
+    pub unscaled_delta : f32,
+
+    /// Scaled seconds since the first update.
+    pub elapsed : f32,
+
+    /// The number of frames since the first update.
+    pub frame_count : u64,
+
+    pub time_scale : f32,
+
+    /// The length of a single simulation step in seconds.
+    pub fixed_delta : f32,
+
+    /// The number of fixed steps run since the first update.
+    pub fixed_step_count : u64,
+
+    accumulator : f32,
+    frame_start : Instant
+}
+
+/// A single frame which takes longer than this only counts as this long, so a stall (e.g. a
+/// breakpoint or window drag) doesn't leave the simulation running hundreds of steps to catch up.
+const MAX_FRAME_DELTA : f32 = 0.25;
+
+impl Default for Time {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Time {
+
+    /// Basic constructor. Time starts counting from the moment it is created, with 60 fixed steps a
+    /// second.
+    pub fn new() -> Time {
+
+        Time {
+            delta : 0.0,
+            unscaled_delta : 0.0,
+            elapsed : 0.0,
+            frame_count : 0,
+            time_scale : 1.0,
+            fixed_delta : 1.0 / 60.0,
+            fixed_step_count : 0,
+            accumulator : 0.0,
+            frame_start : Instant::now()
+        }
+    }
+
+    /// Measures the time since the last update, adds it to the elapsed total and banks it for the
+    /// fixed steps.
+    pub fn update(&mut self) {
+
+        let now = Instant::now();
+
+        self.advance(now.duration_since(self.frame_start).as_secs_f32());
+
+        self.frame_start = now;
+    }
+
+    /// Starts a new frame which took the given number of real seconds, rather than measuring it.
+    /// Used to step the world at a steady rate, e.g. when running headless.
+    pub fn advance(&mut self, seconds : f32) {
+
+        self.unscaled_delta = seconds;
+        self.delta = seconds.min(MAX_FRAME_DELTA) * self.time_scale;
+        self.elapsed += self.delta;
+        self.accumulator += self.delta;
+        self.frame_count += 1;
+    }
+
+    /// Takes one fixed step out of the accumulator. Returns false if there isn't a whole step left.
+    pub fn expend_fixed_step(&mut self) -> bool {
+
+        if self.accumulator < self.fixed_delta {
+            return false
+        }
+
+        self.accumulator -= self.fixed_delta;
+        self.fixed_step_count += 1;
+
+        true
+    }
+
+    /// How far the world is between the last fixed step and the next, from 0.0 to 1.0.
+    pub fn alpha(&self) -> f32 {
+
+        (self.accumulator / self.fixed_delta).min(1.0)
+    }
+
+    /// Real time spent on the current frame so far.
+    pub fn frame_time(&self) -> Duration {
+
+        self.frame_start.elapsed()
+    }
+}
@@ -6,8 +6,8 @@ pub fn get_rotation_angle_2(vec1 : Vector2<f32>, vec2: Vector2<f32>) -> f32 {
 
 pub fn get_point_after_rotation(vec: Vector2<f32>, center: Vector2<f32>, angle: f32 ) -> Vector2<f32> {
 
-    let x = (((vec.x - center.x) * f32::cos(angle)) - ((vec.y - center.y) * f32::sin(angle)) + center.x);
-    let y = (((vec.x - center.x) * f32::sin(angle)) + ((vec.y - center.y) * f32::cos(angle)) + center.y);
+    let x = ((vec.x - center.x) * f32::cos(angle)) - ((vec.y - center.y) * f32::sin(angle)) + center.x;
+    let y = ((vec.x - center.x) * f32::sin(angle)) + ((vec.y - center.y) * f32::cos(angle)) + center.y;
 
     Vector2::new(x, y)
 }
@@ -37,18 +37,17 @@ pub fn get_direction_2d(origin: Vector2<f32>, destination: Vector2<f32>) -> Vect
 
     let heading = destination - origin;
     let distance = Vector2::magnitude(&heading);
-    let direction = heading / distance;
 
-    direction
+    heading / distance
 }
 
 pub fn get_projection_2d(vec: &Vector2<f32>, axis: &Vector2<f32>) -> Vector2<f32> {
 
 //    println!("Initial vec: x: {} y: {}", vec.x, vec.y);
 //    println!("Axis: x: {} y: {}", axis.x, axis.y);
-    let left = vec.dot(&axis);
+    let left = vec.dot(axis);
 //    println!("left: {}", left);
-    let right = axis.dot(&axis);
+    let right = axis.dot(axis);
 //    println!("right: {}", right);
     let scalar = left / right;
 
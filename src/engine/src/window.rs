@@ -28,13 +28,11 @@ impl WindowProperties {
 
     pub fn new(title : &str, width : u32, height : u32) -> WindowProperties {
 
-        let window = WindowProperties {
+        WindowProperties {
             title : String::from(title),
             width,
             height
-        };
-
-        window
+        }
     }
 }
 
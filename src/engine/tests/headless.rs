@@ -0,0 +1,99 @@
+extern crate engine;
+
+use engine::application::App;
+use engine::ecs::*;
+use engine::ecs::schedule::{SystemDescriptor, Stage};
+use engine::game_state::GameState;
+use engine::time::Time;
+use failure::Error;
+use nalgebra::Vector3;
+
+/// Counts the frames the Update stage has run for.
+struct FrameCount(u64);
+
+/// A moving parent with a child offset from it, and an entity which stays still.
+fn spawn_world(state : &mut GameState) -> Result<(), Error> {
+
+    let parent = GameState::create_entity(state)
+        .with(PositionComponent { position : Vector3::new(0.0, 0.0, 0.0) })
+        .with(VelocityComponent { velocity : Vector3::new(120.0, 0.0, 0.0) })
+        .build();
+
+    let child = GameState::create_entity(state)
+        .with(PositionComponent { position : Vector3::new(5.0, 10.0, 0.0) })
+        .build();
+
+    let still = GameState::create_entity(state)
+        .with(PositionComponent { position : Vector3::new(-50.0, -50.0, 0.0) })
+        .build();
+
+    state.set_name(&parent, "parent")?;
+    state.set_name(&child, "child")?;
+    state.set_name(&still, "still")?;
+
+    state.set_parent(&child, &parent)
+}
+
+fn app() -> App {
+
+    App::new()
+        .insert_resource(FrameCount(0))
+        .add_startup_system(SystemDescriptor::new("spawn_world", spawn_world))
+        .add_system(Stage::Update, SystemDescriptor::new("count_frames",
+            |state| { state.resource_mut::<FrameCount>().0 += 1; Ok(()) }))
+}
+
+fn translation(state : &GameState, name : &str) -> Vector3<f32> {
+
+    let entity = state.find_by_name(name).unwrap();
+    let matrix = state.get::<GlobalTransformComponent>(&entity).unwrap().matrix;
+
+    Vector3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)])
+}
+
+#[test]
+fn headless_app_steps_the_world() {
+
+    let mut runner = app().headless().unwrap();
+
+    assert_eq!(runner.state.entity_count(), 3);
+    assert_eq!(runner.state.resource::<FrameCount>().0, 0);
+
+    runner.run_frames(30).unwrap();
+
+    let state = &runner.state;
+
+    // Every headless frame is exactly one fixed step long.
+    assert_eq!(state.resource::<FrameCount>().0, 30);
+    assert_eq!(state.resource::<Time>().fixed_step_count, 30);
+
+    let parent = state.find_by_name("parent").unwrap();
+    let position = state.get::<PositionComponent>(&parent).unwrap().position;
+    let velocity = state.get::<VelocityComponent>(&parent).unwrap().velocity;
+
+    assert!(position.x > 0.0);
+    assert_eq!((position.y, position.z), (0.0, 0.0));
+    assert!(velocity.x > 0.0 && velocity.x < 120.0, "velocity should be damped, was {}", velocity.x);
+
+    // Frames end on a whole fixed step, so transforms sit at the position from before the last one.
+    let previous = state.get::<PreviousPositionComponent>(&parent).unwrap().position;
+
+    assert_eq!(translation(state, "parent"), previous);
+    assert_eq!(translation(state, "child"), previous + Vector3::new(5.0, 10.0, 0.0));
+    assert_eq!(translation(state, "still"), Vector3::new(-50.0, -50.0, 0.0));
+}
+
+#[test]
+fn headless_runs_are_repeatable() {
+
+    let first = app().run_headless(45).unwrap();
+    let second = app().run_headless(45).unwrap();
+
+    let position = |state : &GameState| {
+        let parent = state.find_by_name("parent").unwrap();
+        state.get::<PositionComponent>(&parent).unwrap().position
+    };
+
+    assert_eq!(position(&first), position(&second));
+    assert_eq!(translation(&first, "child"), translation(&second, "child"));
+}
@@ -0,0 +1,27 @@
+//! Events sent by the game's systems. Each is read through an Events<T> resource.
+
+use nalgebra::Vector2;
+use engine::game_state::Entity;
+
+/// Sent when a click lands on an entity's box collider.
+#[derive(Clone, Copy)]
+pub struct CollisionEvent {
+
+    pub entity : Entity,
+    pub cursor_offset : Vector2<f32>
+}
+
+/// Sent when an entity is selected or deselected.
+#[derive(Clone, Copy)]
+pub struct SelectionChanged {
+
+    pub entity : Entity,
+    pub selected : bool
+}
+
+/// Sent when an entity is spawned by a system.
+#[derive(Clone, Copy)]
+pub struct EntitySpawned {
+
+    pub entity : Entity
+}
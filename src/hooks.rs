@@ -0,0 +1,52 @@
+//! HOOKS
+//! SelectedComponent's hooks, registered with the app in game(). They run when the component is
+//! actually added or removed, so selection systems only have to queue the change.
+//! SEE: GameState::on_add, GameState::on_remove
+
+use engine::ecs::ColorComponent;
+use engine::events::event_channel::Events;
+use engine::game_state::{GameState, Entity};
+use crate::components::SelectedComponent;
+use crate::events::SelectionChanged;
+
+/// Announces the selection, then remembers the entity's color and swaps in the selection tint.
+pub fn tint_selected(state : &mut GameState, entity : &Entity) {
+
+    send_selection_changed(state, entity, true);
+
+    let origin_color = match state.get::<ColorComponent>(entity) {
+        Some(color) => color.color,
+        None => return
+    };
+
+    let selected_color = match state.get_mut::<SelectedComponent>(entity) {
+        Some(selected) => { selected.origin_color = origin_color; selected.selected_color },
+        None => return
+    };
+
+    state.get_mut::<ColorComponent>(entity).unwrap().color = selected_color;
+}
+
+pub fn restore_selected_color(state : &mut GameState, entity : &Entity) {
+
+    send_selection_changed(state, entity, false);
+
+    let origin_color = match state.get::<SelectedComponent>(entity) {
+        Some(selected) => selected.origin_color,
+        None => return
+    };
+
+    if let Some(color) = state.get_mut::<ColorComponent>(entity) {
+        color.color = origin_color;
+    }
+}
+
+/// Sent from the hooks, so the event goes out when the component is actually added or removed
+/// rather than when a command to do so is recorded. Nothing is sent without a SelectionChanged
+/// channel.
+fn send_selection_changed(state : &mut GameState, entity : &Entity, selected : bool) {
+
+    if let Some(events) = state.try_resource_mut::<Events<SelectionChanged>>() {
+        events.send(SelectionChanged { entity : *entity, selected });
+    }
+}
@@ -0,0 +1,60 @@
+use engine::ecs::system::System;
+use engine::ecs::{PositionComponent, BoxCollider2DComponent, RotationComponent};
+use failure::Error;
+use engine::ecs::query::With;
+use engine::input::CursorPosition;
+use engine::game_state::GameState;
+use engine::utilities::vector_utils;
+use nalgebra::{Vector3, Vector2};
+use crate::components::{LookAtPositionComponent, SelectedComponent};
+
+pub struct LookAtPositionSystem;
+
+impl<'a> System<'a> for LookAtPositionSystem {
+    type SystemInput = &'a mut GameState;
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let query = input.query::<(&LookAtPositionComponent, &PositionComponent, &mut RotationComponent,
+                                   &mut BoxCollider2DComponent, With<SelectedComponent>)>();
+
+        for (look_at, position, mut rotation, mut collider, _) in query {
+
+            let focus_position = look_at.focus_point;
+            let component_position = Vector2::new(position.position.x, position.position.y);
+
+            let angle_change = vector_utils::get_rotation_angle_2(component_position, focus_position);
+
+            rotation.rotation = Vector3::new(0.0, 0.0, angle_change);
+
+            let corners = vector_utils::get_box_corners(collider.position, collider.size);
+
+            let corners = vector_utils::get_rotated_corners(corners, collider.position, angle_change);
+
+            collider.corners = corners;
+        }
+        Ok(())
+    }
+}
+
+pub struct UpdateFocusPointSystem;
+
+impl<'a> System<'a> for UpdateFocusPointSystem {
+
+    type SystemInput = &'a mut GameState;
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let cursor = input.resource::<CursorPosition>().world;
+
+        for mut look_at in input.query::<&mut LookAtPositionComponent>() {
+
+            // Only written when it moves, so the component isn't flagged as changed every frame.
+            if look_at.focus_point != cursor {
+                look_at.focus_point = cursor;
+            }
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
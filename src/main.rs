@@ -1,10 +1,263 @@
 extern crate engine;
+
+mod components;
+mod events;
+mod hooks;
+mod check_mouse_collision_system;
+mod selection_system;
+mod editor_history_system;
+mod look_at_position_system;
+
+use engine::application::App;
+use engine::ecs::*;
+use engine::ecs::system::System;
+use engine::ecs::schedule::{SystemDescriptor, Stage};
+use engine::events::event_channel::EventReader;
+use engine::game_state::GameState;
+use engine::history::History;
+use engine::input::{MouseInput, KeyCode, CursorPosition, mouse_down, mouse_pressed, key_pressed};
+use engine::prefab::Prefabs;
+use engine::profiler::Profiler;
+use engine::utilities::vector_utils::get_rotation_angle_2;
+use engine::window::WindowProperties;
+use nalgebra::{Vector2, Vector3};
+use serde_json::{json, Value};
+use components::{SelectedComponent, LookAtPositionComponent};
+use events::{CollisionEvent, SelectionChanged, EntitySpawned};
+use look_at_position_system::{LookAtPositionSystem, UpdateFocusPointSystem};
+use editor_history_system::{StartDragSystem, RecordSpawnsSystem};
+
 // Use
 use failure::Error;
 
 fn main() -> Result<(),Error>{
 
-    engine::application::run()?;
+    let args = std::env::args().collect::<Vec<String>>();
+
+    let flag = |name : &str| args.iter().position(|arg| arg == name).map(|index| args.get(index + 1));
+
+    let app = game()?;
+
+    // --headless <frames> runs the simulation without a window, e.g. on CI.
+    if let Some(frames) = flag("--headless") {
+        let frames = frames.map_or(Ok(600), |frames| frames.parse())?;
+        let state = app.run_headless(frames)?;
+        println!("Ran {} frames headless, {} entities alive", frames, state.entity_count());
+        return Ok(())
+    }
+
+    // --record <file> saves the input of a normal run, --replay <file> plays it back headless.
+    if let Some(path) = flag("--record") {
+        return app.record(path.map_or("recording.json", String::as_str))
+    }
+
+    if let Some(path) = flag("--replay") {
+        let state = app.replay(path.map_or("recording.json", String::as_str))?;
+        println!("Replay matched the recording, {} entities alive", state.entity_count());
+        return Ok(())
+    }
+
+    app.run()
+}
+
+/// The box editor: boxes are spawned with the right mouse button and turn toward the cursor while it
+/// is held, crates with space, and anything can be selected and dragged with the left mouse button.
+/// Delete removes the selection, Z and Y undo and redo, and P starts or stops a profiler capture.
+fn game() -> Result<App, Error> {
+
+    let window = WindowProperties::new("Scrapyard Engine", 1280, 720);
+
+    let camera = OrthographicCameraComponent::new(
+        Vector2::new(window.width as f32, window.height as f32),
+        Vector3::new(0.0, 0.0, -1.0));
+
+    let mut collisions = EventReader::<CollisionEvent>::new();
+    let mut selection_changes = EventReader::<SelectionChanged>::new();
+    let mut spawns = EventReader::<EntitySpawned>::new();
+
+    let app = App::new()
+        .with_window(window)
+        .insert_resource(camera)
+        .insert_resource(Prefabs::load_dir("src/prefabs")?)
+        .insert_resource(History::new())
+
+        .add_event::<CollisionEvent>()
+        .add_event::<SelectionChanged>()
+        .add_event::<EntitySpawned>()
+
+        // COMPONENTS
+
+        .on_add::<SelectedComponent>(hooks::tint_selected)
+        .on_remove::<SelectedComponent>(hooks::restore_selected_color)
+        .register_snapshot::<SelectedComponent>()
+        .register_reflect::<SelectedComponent>()
+
+        .register_snapshot::<LookAtPositionComponent>()
+        .register_reflect::<LookAtPositionComponent>()
+        .register_scene_component::<LookAtPositionComponent>("look_at")
+
+        .add_startup_system(SystemDescriptor::new("load_scene",
+            |state| state.load_scene("src/scenes/test_scene.json").map(|_| ())))
+
+        // INPUT
+
+        .add_system(Stage::Input, SystemDescriptor::new("check_collision",
+            |state| check_mouse_collision_system::CheckBoxColliderSystem::run(state))
+            .run_if(|state| mouse_down(state, MouseInput::LeftMouse) && mouse_pressed(state, MouseInput::LeftMouse)))
+
+        .add_system(Stage::Input, SystemDescriptor::new("follow_mouse",
+            |state| selection_system::FollowMouseSystem::run(state))
+            .after("check_collision")
+            .run_if(|state| mouse_down(state, MouseInput::LeftMouse) && !mouse_pressed(state, MouseInput::LeftMouse)))
+
+        .add_system(Stage::Input, SystemDescriptor::new("spawn_box", spawn_box_at_cursor)
+            .run_if(|state| mouse_down(state, MouseInput::RightMouse) && mouse_pressed(state, MouseInput::RightMouse)))
+
+        .add_system(Stage::Input, SystemDescriptor::new("update_focus_point",
+            |state| UpdateFocusPointSystem::run(state))
+            .after("spawn_box")
+            .run_if(|state| mouse_down(state, MouseInput::RightMouse)))
+
+        .add_system(Stage::Input, SystemDescriptor::new("look_at_position",
+            |state| LookAtPositionSystem::run(state))
+            .after("update_focus_point")
+            .run_if(|state| mouse_down(state, MouseInput::RightMouse)))
+
+        .add_system(Stage::Input, SystemDescriptor::new("spawn_crate", spawn_crate)
+            .run_if(|state| key_pressed(state, KeyCode::Space)))
+
+        // EDITOR HISTORY
+
+        .add_system(Stage::Input, SystemDescriptor::new("end_drag",
+            |state| { state.resource_mut::<History>().end_group(); Ok(()) })
+            .run_if(|state| !mouse_down(state, MouseInput::LeftMouse)))
+
+        .add_system(Stage::Input, SystemDescriptor::new("delete_selected",
+            |state| selection_system::DeleteSelectedSystem::run(state))
+            .run_if(|state| key_pressed(state, KeyCode::Delete)))
+
+        .add_system(Stage::Input, SystemDescriptor::new("undo", |state| state.undo().map(|_| ()))
+            .run_if(|state| key_pressed(state, KeyCode::Z)))
+
+        .add_system(Stage::Input, SystemDescriptor::new("redo", |state| state.redo().map(|_| ()))
+            .run_if(|state| key_pressed(state, KeyCode::Y)))
+
+        .add_system(Stage::Input, SystemDescriptor::new("toggle_capture", toggle_capture)
+            .run_if(|state| key_pressed(state, KeyCode::P)))
+
+        // UPDATE
+
+        .add_system(Stage::Update, SystemDescriptor::new("click_selection",
+            move |state| selection_system::ClickSelectionSystem::run((state, &mut collisions)))
+            .run_if(|state| mouse_down(state, MouseInput::LeftMouse) && mouse_pressed(state, MouseInput::LeftMouse)))
+
+        .add_system(Stage::Update, SystemDescriptor::new("start_drag",
+            move |state| StartDragSystem::run((state, &mut selection_changes)))
+            .after("click_selection"))
+
+        .add_system(Stage::Update, SystemDescriptor::new("record_spawns",
+            move |state| RecordSpawnsSystem::run((state, &mut spawns))));
+
+    Ok(app)
+}
+
+/// Starts a profiler capture, or stops the running one, writing it to profile_trace.json and
+/// printing the per-system timings.
+fn toggle_capture(state : &mut GameState) -> Result<(), Error> {
+
+    let profiler = state.resource_mut::<Profiler>();
+
+    if !profiler.is_capturing() {
+        profiler.start_capture();
+        println!("Profiler capture started");
+        return Ok(())
+    }
+
+    let spans = profiler.write_capture("profile_trace.json")?;
+
+    println!("Wrote {} spans to profile_trace.json", spans);
+
+    profiler.print_summary();
+
+    Ok(())
+}
+
+/// Deselects everything and spawns a box at the cursor which looks toward it while the right mouse
+/// button is held.
+fn spawn_box_at_cursor(game_state : &mut GameState) -> Result<(), Error> {
+
+    let screen_coords = game_state.resource::<CursorPosition>().world;
+
+    selection_system::DeselectSystem::run(game_state)?;
+
+    let entity = game_state.spawn_prefab("box", json!({
+        "position": [screen_coords.x, screen_coords.y, 0.0],
+        "box_collider": { "position": [screen_coords.x, screen_coords.y] },
+        "rotation_update": { "axis": [0.0, 0.0, 1.0], "angle": get_rotation_angle_2(screen_coords, screen_coords) },
+        "look_at": [screen_coords.x, screen_coords.y]
+    }))?;
+
+    game_state.commands.insert(entity, SelectedComponent {
+        selected_color: (0.5, 0.5, 0.5, 0.5),
+        origin_color: (0.0, 0.0, 0.0, 0.0),
+        cursor_offset: Vector2::new(0.0, 0.0)
+    });
+
+    game_state.send_event(EntitySpawned { entity });
+
+    Ok(())
+}
+
+/// Spawns a textured crate at the centre of the world.
+fn spawn_crate(game_state : &mut GameState) -> Result<(), Error> {
+
+    let entity = game_state.spawn_prefab("crate", Value::Null)?;
+
+    game_state.send_event(EntitySpawned { entity });
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use engine::headless::HeadlessRunner;
+    use engine::input::recording::InputRecording;
+
+    /// A box spawned with the right mouse button, dragged with the left, then the drag undone.
+    const FIXTURE : &str = "src/recordings/drag_and_undo.json";
+
+    fn replay(recording : &InputRecording, frames : usize) -> HeadlessRunner {
+
+        let mut runner = game().unwrap().headless().unwrap();
+
+        for frame in recording.frames.iter().take(frames) {
+            runner.step_with(frame).unwrap();
+        }
+
+        runner
+    }
+
+    #[test]
+    fn replay_matches_the_recorded_fixture() {
+
+        let recording = InputRecording::load(FIXTURE).unwrap();
+
+        let runner = replay(&recording, recording.frames.len());
+
+        assert_eq!(runner.state.entity_count(), 2);
+
+        recording.check(&runner.state).unwrap();
+    }
+
+    #[test]
+    fn replay_stopped_before_the_undo_does_not_match() {
+
+        let recording = InputRecording::load(FIXTURE).unwrap();
+
+        let runner = replay(&recording, 70);
+
+        assert!(recording.check(&runner.state).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use engine::ecs::system::System;
+use failure::Error;
+use engine::game_state::{GameState, Entity};
+use engine::ecs::{ColorComponent, PositionComponent, PreviousPositionComponent, BoxCollider2DComponent, RotationComponent};
+use engine::ecs::query::With;
+use nalgebra::{Vector3, Vector2};
+use engine::input::CursorPosition;
+use engine::utilities::vector_utils::{get_box_corners, get_rotated_corners};
+use engine::events::event_channel::{Events, EventReader};
+use engine::history::Edit;
+use crate::components::SelectedComponent;
+use crate::events::CollisionEvent;
+
+/// Replaces the current selection with whatever the last click landed on. Selected entities are
+/// tinted, and SelectionChanged is sent, by SelectedComponent's hooks once the command is applied.
+/// SEE: hooks
+pub struct ClickSelectionSystem;
+
+impl<'a> System<'a> for ClickSelectionSystem {
+
+    type SystemInput = (&'a mut GameState, &'a mut EventReader<CollisionEvent>);
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let (state, reader) = input;
+
+        let collision = reader.read(state.resource::<Events<CollisionEvent>>()).last().cloned();
+
+        DeselectSystem::run(state)?;
+
+        if let Some(collision) = collision {
+
+            if state.has::<ColorComponent>(&collision.entity) {
+
+                // The origin color is filled in when the component is added.
+                state.commands.insert(collision.entity, SelectedComponent { selected_color: (0.7, 0.7, 0.7, 0.5),
+                    origin_color: (0.0, 0.0, 0.0, 0.0), cursor_offset: collision.cursor_offset});
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct DeselectSystem;
+
+impl<'a> System<'a> for DeselectSystem {
+
+    type SystemInput = &'a mut GameState;
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let selected = input.query::<(Entity, With<SelectedComponent>)>()
+            .map(|(idx, _)| idx)
+            .collect::<Vec<Entity>>();
+
+        for idx in selected {
+
+            // Removed once the system has finished, so nothing is removed mid-iteration. The original
+            // color is put back when the component is removed.
+            input.commands.remove::<SelectedComponent>(idx);
+        }
+
+        Ok(())
+    }
+}
+
+/// Despawns every selected entity, recording each one so it can be undone.
+pub struct DeleteSelectedSystem;
+
+impl<'a> System<'a> for DeleteSelectedSystem {
+
+    type SystemInput = &'a mut GameState;
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let selected = input.query::<(Entity, With<SelectedComponent>)>()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<Entity>>();
+
+        for entity in selected.iter() {
+
+            input.record_edit(Edit::despawn(input, entity));
+            input.despawn(entity);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct FollowMouseSystem;
+
+impl<'a> System<'a> for FollowMouseSystem {
+    type SystemInput = &'a mut GameState;
+
+    fn run(input: Self::SystemInput) -> Result<(), Error> {
+
+        let cursor = input.resource::<CursorPosition>().world;
+
+        let cursor_pos = Vector3::new(cursor.x, cursor.y, 0.0);
+
+        let query = input.query::<(Entity, &SelectedComponent, &mut PositionComponent, &RotationComponent,
+                                     &mut BoxCollider2DComponent, Option<&mut PreviousPositionComponent>)>();
+
+        let mut moves = Vec::new();
+
+        for (entity, selected, mut position, rotation, mut collider, previous) in query {
+
+            let before = ((*position).clone(), (*collider).clone());
+
+            let offset = Vector3::new(selected.cursor_offset.x, selected.cursor_offset.y, 0.0);
+
+            position.position = cursor_pos + offset;
+
+            // Dragged entities jump straight to the cursor rather than being interpolated.
+            if let Some(mut previous) = previous {
+                previous.position = position.position;
+            }
+
+            let collider_pos = cursor_pos + offset;
+
+            let coords = Vector2::new(collider_pos.x, collider_pos.y);
+
+            let corners = get_box_corners(coords, collider.size);
+
+            let angle_rotation = rotation.rotation.z;
+
+            let corners = get_rotated_corners(corners, collider.position, angle_rotation);
+
+            collider.position = coords;
+
+            collider.corners = corners;
+
+            moves.push((entity, before, ((*position).clone(), (*collider).clone())));
+        }
+
+        for (entity, (position, collider), (new_position, new_collider)) in moves {
+            input.record_edit(Edit::set(&entity, Some(position), Some(new_position)));
+            input.record_edit(Edit::set(&entity, Some(collider), Some(new_collider)));
+        }
+
+        Ok(())
+    }
+}
+